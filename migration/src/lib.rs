@@ -3,6 +3,17 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_transaction_table;
 mod m20220101_000002_create_counterparty_table;
 mod m20220101_000003_create_account_table;
+mod m20230601_000004_convert_amount_to_minor_units;
+mod m20230601_000005_create_sync_state_table;
+mod m20230601_000006_create_space_table;
+mod m20230601_000007_add_category_uid_to_transaction;
+mod m20230601_000008_add_source_amount_to_transaction;
+mod m20230601_000009_create_exchange_rate_table;
+mod m20230601_000010_add_feed_item_details_to_transaction;
+mod m20230601_000011_add_keys_and_indexes;
+mod m20230601_000012_widen_account_token;
+mod m20230601_000013_add_identifiers_to_account;
+mod m20230601_000014_add_transfer_uid_to_transaction;
 
 pub struct Migrator;
 
@@ -13,6 +24,17 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_transaction_table::Migration),
             Box::new(m20220101_000002_create_counterparty_table::Migration),
             Box::new(m20220101_000003_create_account_table::Migration),
+            Box::new(m20230601_000004_convert_amount_to_minor_units::Migration),
+            Box::new(m20230601_000005_create_sync_state_table::Migration),
+            Box::new(m20230601_000006_create_space_table::Migration),
+            Box::new(m20230601_000007_add_category_uid_to_transaction::Migration),
+            Box::new(m20230601_000008_add_source_amount_to_transaction::Migration),
+            Box::new(m20230601_000009_create_exchange_rate_table::Migration),
+            Box::new(m20230601_000010_add_feed_item_details_to_transaction::Migration),
+            Box::new(m20230601_000011_add_keys_and_indexes::Migration),
+            Box::new(m20230601_000012_widen_account_token::Migration),
+            Box::new(m20230601_000013_add_identifiers_to_account::Migration),
+            Box::new(m20230601_000014_add_transfer_uid_to_transaction::Migration),
         ]
     }
 }
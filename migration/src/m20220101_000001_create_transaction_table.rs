@@ -23,9 +23,10 @@ impl MigrationTrait for Migration {
                     )
                     .col(ColumnDef::new(Transaction::Uid).string().not_null())
                     .col(ColumnDef::new(Transaction::AccountUid).string().not_null())
+                    // with time zone, so Postgres can decode it as `DateTime<Utc>`
                     .col(
                         ColumnDef::new(Transaction::TransactionTime)
-                            .timestamp()
+                            .timestamp_with_time_zone()
                             .not_null(),
                     )
                     .col(
@@ -56,6 +57,7 @@ impl MigrationTrait for Migration {
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Iden)]
 pub enum Transaction {
     Table,
@@ -22,7 +22,11 @@ impl MigrationTrait for Migration {
                     )
                     .col(ColumnDef::new(Account::Name).string().not_null())
                     .col(ColumnDef::new(Account::Uid).string().not_null())
-                    .col(ColumnDef::new(Account::CreatedAt).timestamp().not_null())
+                    .col(
+                        ColumnDef::new(Account::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
                     .col(ColumnDef::new(Account::DefaultCategory).string().not_null())
                     .col(ColumnDef::new(Account::Token).string_len(1400).not_null())
                     .to_owned(),
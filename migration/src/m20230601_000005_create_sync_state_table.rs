@@ -0,0 +1,63 @@
+// m20230601_000005_create_sync_state_table.rs
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Create the SyncState table.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SyncState::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SyncState::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SyncState::AccountUid).string().not_null())
+                    .col(ColumnDef::new(SyncState::CategoryUid).string().not_null())
+                    .col(
+                        ColumnDef::new(SyncState::SyncedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-sync_state-account_uid-category_uid")
+                    .table(SyncState::Table)
+                    .col(SyncState::AccountUid)
+                    .col(SyncState::CategoryUid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    // Drop the SyncState table.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SyncState::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SyncState {
+    Table,
+    Id,
+    AccountUid,
+    CategoryUid,
+    SyncedAt,
+}
@@ -0,0 +1,60 @@
+// m20230601_000006_create_space_table.rs
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Create the Space table.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Space::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Space::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Space::AccountUid).string().not_null())
+                    .col(ColumnDef::new(Space::Uid).string().not_null())
+                    .col(ColumnDef::new(Space::Name).string().not_null())
+                    .col(ColumnDef::new(Space::Kind).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-space-uid")
+                    .table(Space::Table)
+                    .col(Space::Uid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    // Drop the Space table.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Space::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Space {
+    Table,
+    Id,
+    AccountUid,
+    Uid,
+    Name,
+    Kind,
+}
@@ -0,0 +1,81 @@
+// m20230601_000007_add_category_uid_to_transaction.rs
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Record the feed category of each transaction.
+    //
+    // Existing transactions were all fetched from their account's default category.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .add_column(
+                        ColumnDef::new(Transaction::CategoryUid)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let default_category = Query::select()
+            .column((Account::Table, Account::DefaultCategory))
+            .from(Account::Table)
+            .and_where(
+                Expr::col((Account::Table, Account::Uid))
+                    .equals((Transaction::Table, Transaction::AccountUid)),
+            )
+            .to_owned();
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Transaction::Table)
+                    .value(
+                        Transaction::CategoryUid,
+                        Func::coalesce([
+                            SimpleExpr::SubQuery(
+                                None,
+                                Box::new(default_category.into_sub_query_statement()),
+                            ),
+                            Expr::val("").into(),
+                        ]),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    // Drop the category column.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .drop_column(Transaction::CategoryUid)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Transaction {
+    Table,
+    AccountUid,
+    CategoryUid,
+}
+
+#[derive(Iden)]
+pub enum Account {
+    Table,
+    Uid,
+    DefaultCategory,
+}
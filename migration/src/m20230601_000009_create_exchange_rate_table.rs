@@ -0,0 +1,66 @@
+// m20230601_000009_create_exchange_rate_table.rs
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Create the ExchangeRate table.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExchangeRate::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ExchangeRate::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ExchangeRate::Date).date().not_null())
+                    .col(
+                        ColumnDef::new(ExchangeRate::FromCurrency)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ExchangeRate::ToCurrency).string().not_null())
+                    .col(ColumnDef::new(ExchangeRate::Rate).double().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-exchange_rate-date-from_currency-to_currency")
+                    .table(ExchangeRate::Table)
+                    .col(ExchangeRate::Date)
+                    .col(ExchangeRate::FromCurrency)
+                    .col(ExchangeRate::ToCurrency)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    // Drop the ExchangeRate table.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ExchangeRate::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ExchangeRate {
+    Table,
+    Id,
+    Date,
+    FromCurrency,
+    ToCurrency,
+    Rate,
+}
@@ -0,0 +1,115 @@
+// m20230601_000010_add_feed_item_details_to_transaction.rs
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Add columns for the rest of the feed item, and for the raw JSON payload.
+    //
+    // Each column is added in a separate statement, as SQLite only allows one change per
+    // `ALTER TABLE`.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for mut column in columns() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Transaction::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Drop the added columns.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in columns().iter().rev() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Transaction::Table)
+                        .drop_column(Alias::new(&column.get_column_name()))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn columns() -> Vec<ColumnDef> {
+    vec![
+        ColumnDef::new(Transaction::Mcc).integer().null().to_owned(),
+        ColumnDef::new(Transaction::TransactingUserUid)
+            .string()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::SettlementTime)
+            .timestamp_with_time_zone()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::UpdatedAt)
+            .timestamp_with_time_zone()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::Source)
+            .string()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::Country)
+            .string()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::RoundUpAmount)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::RoundUpGoalUid)
+            .string()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::HasAttachment)
+            .boolean()
+            .not_null()
+            .default(false)
+            .to_owned(),
+        ColumnDef::new(Transaction::HasReceipt)
+            .boolean()
+            .not_null()
+            .default(false)
+            .to_owned(),
+        ColumnDef::new(Transaction::BatchPaymentUid)
+            .string()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::BatchPaymentType)
+            .string()
+            .null()
+            .to_owned(),
+        ColumnDef::new(Transaction::Raw).text().null().to_owned(),
+    ]
+}
+
+#[derive(Iden)]
+pub enum Transaction {
+    Table,
+    Mcc,
+    TransactingUserUid,
+    SettlementTime,
+    UpdatedAt,
+    Source,
+    Country,
+    RoundUpAmount,
+    RoundUpGoalUid,
+    HasAttachment,
+    HasReceipt,
+    BatchPaymentUid,
+    BatchPaymentType,
+    Raw,
+}
@@ -0,0 +1,197 @@
+// m20230601_000011_add_keys_and_indexes.rs
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Make UIDs unique, index transactions by time and link transactions to their account and
+    // counterparty.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can't add a foreign key to an existing table, so there the table is rebuilt with
+        // them, after the unique indexes they refer to and before the table's own indexes
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            let mut referenced = indexes();
+            let own = referenced.split_off(2);
+            for index in referenced {
+                manager.create_index(index).await?;
+            }
+            rebuild_sqlite_transaction_table(manager, |columns| {
+                format!("{}, {}", columns, SQLITE_FOREIGN_KEYS)
+            })
+            .await?;
+            for index in own {
+                manager.create_index(index).await?;
+            }
+            return Ok(());
+        }
+
+        for index in indexes() {
+            manager.create_index(index).await?;
+        }
+        for foreign_key in foreign_keys() {
+            manager.create_foreign_key(foreign_key).await?;
+        }
+
+        Ok(())
+    }
+
+    // Drop the foreign keys and indexes.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let sqlite = manager.get_database_backend() == DbBackend::Sqlite;
+        if !sqlite {
+            for name in [
+                "fk-transaction-counterparty_id",
+                "fk-transaction-account_uid",
+            ] {
+                manager
+                    .drop_foreign_key(
+                        ForeignKey::drop()
+                            .name(name)
+                            .table(Transaction::Table)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+
+        let indexes = [
+            ("idx-account-uid", Account::Table.into_iden()),
+            ("idx-counterparty-uid", Counterparty::Table.into_iden()),
+            ("idx-transaction-uid", Transaction::Table.into_iden()),
+            (
+                "idx-transaction-transaction_time",
+                Transaction::Table.into_iden(),
+            ),
+        ];
+        for (name, table) in indexes {
+            manager
+                .drop_index(Index::drop().name(name).table(table).to_owned())
+                .await?;
+        }
+        if sqlite {
+            rebuild_sqlite_transaction_table(manager, |columns| {
+                columns.replace(&format!(", {}", SQLITE_FOREIGN_KEYS), "")
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+// The foreign keys of `foreign_keys`, as table constraints for SQLite
+const SQLITE_FOREIGN_KEYS: &str = "CONSTRAINT \"fk-transaction-counterparty_id\" \
+    FOREIGN KEY (\"counterparty_id\") REFERENCES \"counterparty\" (\"id\"), \
+    CONSTRAINT \"fk-transaction-account_uid\" \
+    FOREIGN KEY (\"account_uid\") REFERENCES \"account\" (\"uid\")";
+
+// Rebuild the SQLite transaction table, as SQLite's `ALTER TABLE` can't change constraints: create
+// a copy whose column and constraint definitions are those of the table passed through `change`,
+// copy the rows across, drop the table and rename the copy.
+async fn rebuild_sqlite_transaction_table(
+    manager: &SchemaManager<'_>,
+    change: impl Fn(&str) -> String,
+) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let row = db
+        .query_one(Statement::from_string(
+            DbBackend::Sqlite,
+            String::from(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'transaction'",
+            ),
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Migration(String::from("no transaction table")))?;
+    let sql: String = row.try_get("", "sql")?;
+    // the definitions between the outermost parentheses of `CREATE TABLE "transaction" (...)`
+    let definitions = sql
+        .find('(')
+        .zip(sql.rfind(')'))
+        .map(|(start, end)| &sql[start + 1..end])
+        .ok_or_else(|| DbErr::Migration(format!("unexpected table definition: {}", sql)))?;
+
+    for statement in [
+        format!(
+            "CREATE TABLE \"transaction_new\" ({})",
+            change(definitions.trim())
+        ),
+        String::from("INSERT INTO \"transaction_new\" SELECT * FROM \"transaction\""),
+        String::from("DROP TABLE \"transaction\""),
+        String::from("ALTER TABLE \"transaction_new\" RENAME TO \"transaction\""),
+    ] {
+        db.execute(Statement::from_string(DbBackend::Sqlite, statement))
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn indexes() -> Vec<IndexCreateStatement> {
+    vec![
+        Index::create()
+            .name("idx-account-uid")
+            .table(Account::Table)
+            .col(Account::Uid)
+            .unique()
+            .to_owned(),
+        Index::create()
+            .name("idx-counterparty-uid")
+            .table(Counterparty::Table)
+            .col(Counterparty::Uid)
+            .unique()
+            .to_owned(),
+        Index::create()
+            .name("idx-transaction-uid")
+            .table(Transaction::Table)
+            .col(Transaction::Uid)
+            .unique()
+            .to_owned(),
+        Index::create()
+            .name("idx-transaction-transaction_time")
+            .table(Transaction::Table)
+            .col(Transaction::TransactionTime)
+            .to_owned(),
+    ]
+}
+
+fn foreign_keys() -> Vec<ForeignKeyCreateStatement> {
+    vec![
+        ForeignKey::create()
+            .name("fk-transaction-counterparty_id")
+            .from(Transaction::Table, Transaction::CounterpartyId)
+            .to(Counterparty::Table, Counterparty::Id)
+            .to_owned(),
+        ForeignKey::create()
+            .name("fk-transaction-account_uid")
+            .from(Transaction::Table, Transaction::AccountUid)
+            .to(Account::Table, Account::Uid)
+            .to_owned(),
+    ]
+}
+
+#[derive(Iden)]
+pub enum Account {
+    Table,
+    Uid,
+}
+
+#[derive(Iden)]
+pub enum Counterparty {
+    Table,
+    Id,
+    Uid,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Iden)]
+pub enum Transaction {
+    Table,
+    Uid,
+    AccountUid,
+    CounterpartyId,
+    TransactionTime,
+}
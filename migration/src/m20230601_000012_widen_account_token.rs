@@ -0,0 +1,50 @@
+// m20230601_000012_widen_account_token.rs
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Make room for encrypted access tokens, which are longer than the tokens themselves.
+    //
+    // SQLite doesn't enforce string lengths, and can't alter a column, so it is left as it is.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Account::Table)
+                    .modify_column(ColumnDef::new(Account::Token).text().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    // Restore the original token length.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Account::Table)
+                    .modify_column(ColumnDef::new(Account::Token).string_len(1400).not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Account {
+    Table,
+    Token,
+}
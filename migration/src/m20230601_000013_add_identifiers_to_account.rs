@@ -0,0 +1,99 @@
+// m20230601_000013_add_identifiers_to_account.rs
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    // Add the account holder and bank identifiers of each account, and the own account a
+    // transaction's counterparty is, if it is one.
+    //
+    // Each column is added in a separate statement, as SQLite only allows one change per
+    // `ALTER TABLE`.
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for mut column in account_columns() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Account::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .add_column(
+                        ColumnDef::new(Transaction::CounterpartyAccountUid)
+                            .string()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    // Drop the added columns.
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .drop_column(Transaction::CounterpartyAccountUid)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in account_columns().iter().rev() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Account::Table)
+                        .drop_column(Alias::new(&column.get_column_name()))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn account_columns() -> Vec<ColumnDef> {
+    [
+        Account::AccountHolderUid,
+        Account::AccountHolderType,
+        Account::AccountHolderName,
+        Account::SortCode,
+        Account::AccountNumber,
+        Account::Iban,
+        Account::Bic,
+    ]
+    .into_iter()
+    .map(|column| ColumnDef::new(column).string().null().to_owned())
+    .collect()
+}
+
+#[derive(Iden)]
+#[allow(clippy::enum_variant_names)]
+pub enum Account {
+    Table,
+    AccountHolderUid,
+    AccountHolderType,
+    AccountHolderName,
+    SortCode,
+    AccountNumber,
+    Iban,
+    Bic,
+}
+
+#[derive(Iden)]
+pub enum Transaction {
+    Table,
+    CounterpartyAccountUid,
+}
@@ -3,15 +3,16 @@
 //! Transactionsa are stored in a database. Reports can be produced in [ledger](https://ledger-cli.org/features.html) format.
 
 use anyhow::Result;
-use clap::{arg, Command};
+use chrono::NaiveDate;
+use clap::{arg, Arg, ArgMatches, Command};
 use money::commands::{self};
+use money::export::Format;
+use money::starling::error::StarlingError;
 use std::{fs, process};
 
 /// Commands
 ///
 /// See: https://github.com/clap-rs/clap/blob/master/examples/git.rs
-///
-
 fn cli() -> Command {
     Command::new("money")
         .about("A money managing app")
@@ -26,6 +27,33 @@ fn cli() -> Command {
                     Command::new("add")
                         .about("Add an account")
                         .arg(arg!(-f --"filename" <APITOKEN> "filename with token")),
+                )
+                .subcommand(
+                    Command::new("rotate-key")
+                        .about("Re-encrypt the stored tokens with a new passphrase or key file")
+                        .arg(arg!(--"key-file" <FILE> "Write a new random key to this file and use it instead of a passphrase")),
+                ),
+        )
+        .subcommand(
+            Command::new("token")
+                .about("Access token commands")
+                .arg_required_else_help(true)
+                .subcommand(Command::new("list").about("List tokens and the accounts using them"))
+                .subcommand(
+                    Command::new("verify")
+                        .about("Show the scopes and expiry of each token")
+                        .arg(arg!([ACCOUNT] "Only the token this account (name or UID) uses")),
+                )
+                .subcommand(
+                    Command::new("replace")
+                        .about("Replace the token of every account a new token can see")
+                        .arg(arg!(-f --"filename" <APITOKEN> "filename with the new token").required(true)),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove a token and the accounts using it")
+                        .arg(arg!(<ACCOUNT> "An account (name or UID) using the token"))
+                        .arg(arg!(--transactions "Delete the accounts' transactions too")),
                 ),
         )
         .subcommand(
@@ -33,15 +61,76 @@ fn cli() -> Command {
                 .about("Account commands")
                 .arg_required_else_help(true)
                 .subcommand(Command::new("list").about("List accounts"))
+                .subcommand(
+                    Command::new("refresh")
+                        .about("Fetch account holder details and sort codes, account numbers and IBANs"),
+                )
                 .subcommand(Command::new("balance").about("Account balances")),
         )
+        .subcommand(
+            Command::new("rates")
+                .about("Exchange rate commands")
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("import")
+                        .about("Import exchange rates from a date,from,to,rate CSV file")
+                        .arg(arg!(<FILE> "CSV file of exchange rates")),
+                ),
+        )
+        .subcommand(
+            Command::new("spaces")
+                .about("Spaces (savings goals and spending spaces) commands")
+                .arg_required_else_help(true)
+                .subcommand(Command::new("list").about("List spaces"))
+                .subcommand(Command::new("balance").about("Space balances")),
+        )
         .subcommand(
             Command::new("transactions")
                 .about("get transactions")
-                .arg(arg!(days: [DAYS] "The days to get").default_value("31")),
+                .arg(arg!(days: [DAYS] "The days to get").default_value("31"))
+                .arg(
+                    arg!(--from <DATE> "Get settled transactions from this date (YYYY-MM-DD)")
+                        .conflicts_with("days"),
+                )
+                .arg(
+                    arg!(--to <DATE> "Get settled transactions up to this date (YYYY-MM-DD)")
+                        .requires("from"),
+                )
+                .args_conflicts_with_subcommands(true)
+                .subcommand(
+                    Command::new("sync")
+                        .about("Get transactions changed since the last sync")
+                        .arg(arg!(--full "Ignore the last sync and get all transactions")),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export transactions")
+                .arg(
+                    arg!(-f --format <FORMAT> "The output format")
+                        .value_parser(["ledger", "hledger", "beancount"])
+                        .default_value("ledger"),
+                )
+                .args(export_args())
+                .args_conflicts_with_subcommands(true)
+                .subcommand(
+                    Command::new("ledger")
+                        .about("Export a ledger-cli journal (same as --format ledger)")
+                        .args(export_args()),
+                ),
         )
 }
 
+// Options shared by `export` and `export ledger`
+fn export_args() -> [Arg; 4] {
+    [
+        arg!(--from <DATE> "Earliest transaction date (YYYY-MM-DD)"),
+        arg!(--to <DATE> "Latest transaction date (YYYY-MM-DD)"),
+        arg!(--category <CATEGORY> "Only transactions in this space (name) or category (UID)"),
+        arg!(-o --output <FILE> "File to write (default: stdout)"),
+    ]
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let matches = cli().get_matches();
@@ -52,8 +141,7 @@ async fn main() -> Result<()> {
             match admin_command {
                 ("init", _) => {
                     if let Err(e) = commands::admin::initialise().await {
-                        println!("Application error: {}", e);
-                        process::exit(1);
+                        exit_with(e);
                     }
                 }
 
@@ -62,8 +150,52 @@ async fn main() -> Result<()> {
                     let contents = fs::read_to_string(filepath).expect("opening file");
                     let token = contents.trim().to_string();
                     if let Err(e) = commands::admin::add_account(&token).await {
-                        println!("Application error: {}", e);
-                        process::exit(1);
+                        exit_with(e);
+                    }
+                }
+
+                ("rotate-key", sub_matches) => {
+                    let key_file = sub_matches.get_one::<String>("key-file");
+                    if let Err(e) = commands::admin::rotate_key(key_file).await {
+                        exit_with(e);
+                    }
+                }
+
+                (name, _) => {
+                    unreachable!("Unsupported command `{name}`")
+                }
+            }
+        }
+
+        Some(("token", sub_matches)) => {
+            let token_command = sub_matches.subcommand().unwrap();
+            match token_command {
+                ("list", _) => {
+                    if let Err(e) = commands::token::list().await {
+                        exit_with(e);
+                    }
+                }
+
+                ("verify", sub_matches) => {
+                    let account = sub_matches.get_one::<String>("ACCOUNT");
+                    if let Err(e) = commands::token::verify(account).await {
+                        exit_with(e);
+                    }
+                }
+
+                ("replace", sub_matches) => {
+                    let filepath = sub_matches.get_one::<String>("filename").unwrap();
+                    let token = read_token(filepath).unwrap_or_else(|e| exit_with(e));
+                    if let Err(e) = commands::token::replace(&token).await {
+                        exit_with(e);
+                    }
+                }
+
+                ("remove", sub_matches) => {
+                    let account = sub_matches.get_one::<String>("ACCOUNT").unwrap();
+                    let transactions = sub_matches.get_flag("transactions");
+                    if let Err(e) = commands::token::remove(account, transactions).await {
+                        exit_with(e);
                     }
                 }
 
@@ -77,11 +209,58 @@ async fn main() -> Result<()> {
             let account_command = sub_matches.subcommand().unwrap();
             match account_command {
                 ("list", _) => {
-                    commands::account::list().await?;
+                    if let Err(e) = commands::account::list().await {
+                        exit_with(e);
+                    }
+                }
+
+                ("refresh", _) => {
+                    if let Err(e) = commands::account::refresh().await {
+                        exit_with(e);
+                    }
                 }
 
                 ("balance", _) => {
-                    commands::account::balance().await?;
+                    if let Err(e) = commands::account::balance().await {
+                        exit_with(e);
+                    }
+                }
+
+                (name, _) => {
+                    unreachable!("Unsupported command `{name}`")
+                }
+            }
+        }
+
+        Some(("rates", sub_matches)) => {
+            let rates_command = sub_matches.subcommand().unwrap();
+            match rates_command {
+                ("import", sub_matches) => {
+                    let filename = sub_matches.get_one::<String>("FILE").unwrap();
+                    if let Err(e) = commands::rates::import(filename).await {
+                        exit_with(e);
+                    }
+                }
+
+                (name, _) => {
+                    unreachable!("Unsupported command `{name}`")
+                }
+            }
+        }
+
+        Some(("spaces", sub_matches)) => {
+            let spaces_command = sub_matches.subcommand().unwrap();
+            match spaces_command {
+                ("list", _) => {
+                    if let Err(e) = commands::spaces::list().await {
+                        exit_with(e);
+                    }
+                }
+
+                ("balance", _) => {
+                    if let Err(e) = commands::spaces::balance().await {
+                        exit_with(e);
+                    }
                 }
 
                 (name, _) => {
@@ -92,6 +271,23 @@ async fn main() -> Result<()> {
 
         Some(("transactions", sub_matches)) => {
             println!("Processing transactions");
+
+            if let Some(("sync", sync_matches)) = sub_matches.subcommand() {
+                let full = sync_matches.get_flag("full");
+                if let Err(e) = commands::transactions::sync(full).await {
+                    exit_with(e);
+                }
+                return Ok(());
+            }
+
+            if let Some(from) = date_arg(sub_matches, "from") {
+                let to = date_arg(sub_matches, "to");
+                if let Err(e) = commands::transactions::update_between(from, to).await {
+                    exit_with(e);
+                }
+                return Ok(());
+            }
+
             let days = sub_matches
                 .get_one::<String>("days")
                 .map(|s| s.as_str())
@@ -101,8 +297,29 @@ async fn main() -> Result<()> {
             println!("Getting {} days", days);
 
             if let Err(e) = commands::transactions::update(days).await {
-                println!("Application error: {}", e);
-                process::exit(1);
+                exit_with(e);
+            }
+        }
+
+        Some(("export", sub_matches)) => {
+            // `export ledger` is the original form of `export --format ledger`
+            let (format, sub_matches): (Format, _) = match sub_matches.subcommand() {
+                Some(("ledger", ledger_matches)) => (Format::Ledger, ledger_matches),
+                _ => (
+                    sub_matches
+                        .get_one::<String>("format")
+                        .unwrap()
+                        .parse()
+                        .unwrap(),
+                    sub_matches,
+                ),
+            };
+            let from = date_arg(sub_matches, "from");
+            let to = date_arg(sub_matches, "to");
+            let category = sub_matches.get_one::<String>("category");
+            let output = sub_matches.get_one::<String>("output");
+            if let Err(e) = commands::export::export(format, from, to, category, output).await {
+                exit_with(e);
             }
         }
         _ => unreachable!(),
@@ -110,3 +327,35 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+// Report an error and exit, with a specific message and exit code for Starling API failures
+fn exit_with(e: anyhow::Error) -> ! {
+    match e.downcast_ref::<StarlingError>() {
+        Some(error) => {
+            println!("Starling API error: {}", error);
+            process::exit(error.exit_code());
+        }
+        None => {
+            println!("Application error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// Read an access token from a file, without surrounding whitespace
+fn read_token(filepath: &str) -> Result<String> {
+    let contents = fs::read_to_string(filepath)
+        .map_err(|e| anyhow::anyhow!("can't read the token file `{}`: {}", filepath, e))?;
+
+    Ok(contents.trim().to_string())
+}
+
+// Parse an optional `YYYY-MM-DD` date argument, exiting with a message if it is malformed
+fn date_arg(matches: &ArgMatches, name: &str) -> Option<NaiveDate> {
+    matches.get_one::<String>(name).map(|s| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap_or_else(|_| {
+            println!("Invalid date for --{}: `{}` (expected YYYY-MM-DD)", name, s);
+            process::exit(1);
+        })
+    })
+}
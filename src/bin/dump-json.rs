@@ -1,13 +1,53 @@
-// Script to dump the feed_item as a JSON file, for testing aginst
+//! `dump-json` records live Starling API responses as a JSON fixture for `StarlingMockClient`.
+//!
+//! The fixture holds every account for the token, with its balance and recent feed items. Use
+//! `--redact` to replace names, UIDs, references and amounts before sharing it.
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use clap::{arg, value_parser, Command};
+use money::config::Config;
+use money::starling::client::StarlingApiClient;
+use money::starling::fixture;
+use std::fs;
+
+fn cli() -> Command {
+    Command::new("dump-json")
+        .about("Record Starling API responses as a JSON test fixture")
+        .arg(arg!(-f --filename <APITOKEN> "filename with token").required(true))
+        .arg(
+            arg!(--days <DAYS> "Days of feed items to record")
+                .value_parser(value_parser!(i64))
+                .default_value("90"),
+        )
+        .arg(arg!(--redact "Replace names, UIDs, references and amounts with placeholders"))
+        .arg(arg!(-o --output <FILE> "File to write (default: stdout)"))
+}
 
 #[tokio::main]
-async fn main() {
-    // dotenvy::dotenv().ok();
-    // let personal_token =
-    //     env::var("PERSONAL_TOKEN").expect("PERSONAL_TOKEN is not set in .env file");
-    // let client = StarlingApiClient::new(personal_token);
-
-    // for account in client.accounts().await {
-    //     println!("{:#?}", account);
-    // }
+async fn main() -> Result<()> {
+    let matches = cli().get_matches();
+
+    let filepath = matches.get_one::<String>("filename").unwrap();
+    let token = fs::read_to_string(filepath)
+        .with_context(|| format!("reading token from `{}`", filepath))?
+        .trim()
+        .to_string();
+    let days = *matches.get_one::<i64>("days").unwrap();
+
+    // Use the configured API URL and retry policy, if there is a config file
+    let config = Config::new();
+    let client = StarlingApiClient::from_config(&token, &config.starling);
+
+    let mut recorded = fixture::record(&client, Utc::now() - Duration::days(days)).await?;
+    if matches.get_flag("redact") {
+        recorded = fixture::redact(&recorded);
+    }
+
+    match matches.get_one::<String>("output") {
+        Some(path) => recorded.save_fixture(path)?,
+        None => println!("{}", serde_json::to_string_pretty(&recorded)?),
+    }
+
+    Ok(())
 }
@@ -1,24 +1,102 @@
 //! Command Line Interface `Accounts` commands
 //!
 
-use crate::{db, starling::client::StarlingApiClient};
-use anyhow::Result;
+use super::{api_client, token_key};
+use crate::config::Config;
+use crate::db;
+use crate::money::Money;
+use crate::starling::client::StarlingClient;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
 
 pub async fn list() -> Result<()> {
     println!("Account list:");
-    for account in db::account::list().await? {
+    let db = db::get_database().await?;
+    for account in db::account::list(&db).await? {
         println!("- {:#?}", account.name);
+        if let (Some(sort_code), Some(account_number)) =
+            (&account.sort_code, &account.account_number)
+        {
+            println!(
+                "  sort code {}, account number {}",
+                sort_code, account_number
+            );
+        }
+        if let Some(iban) = &account.iban {
+            println!("  IBAN {}", iban);
+        }
     }
 
     Ok(())
 }
 
+/// Fetch the account holder and bank identifiers of every stored account, then recognise the
+/// stored transactions whose counterparty is one of them
+pub async fn refresh() -> Result<()> {
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let client_for = api_client(&config, &key);
+    for account in db::account::list(&db).await? {
+        let client = client_for(&account)?;
+        match db::account::refresh_details(&db, &client, &[account.uid.to_owned()]).await? {
+            true => println!("- {}: refreshed", account.name),
+            false => println!("- {}: skipped", account.name),
+        }
+    }
+    let matched = db::transaction::match_own_accounts(&db).await?;
+    println!("{} transaction(s) matched with own accounts", matched);
+    let linked = db::transaction::link_transfers(&db, None).await?;
+    println!("{} transfer(s) between own accounts linked", linked);
+
+    Ok(())
+}
+
+/// Show each account's balance, and the total in the home currency using the latest imported
+/// exchange rates
 pub async fn balance() -> Result<()> {
     println!("Account balances:");
-    for account in db::account::list().await? {
-        let client = StarlingApiClient::new(&account.token);
-        let balance = client.balance(&account.uid).await?;
-        println!("- {}: {}", account.name, balance.effective.as_string());
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let client_for = api_client(&config, &key);
+    let home = &config.home_currency;
+    let today = Utc::now().date_naive();
+    let mut total = Money::new(0, home);
+    let mut unconverted = Vec::new();
+
+    for account in db::account::list(&db).await? {
+        let client = client_for(&account)?;
+        let balance = client.balance(&account.uid).await?.effective.as_money();
+        let rate = db::exchange_rate::rate_on(&db, &balance.currency, home, today).await?;
+        match rate {
+            Some(rate) => {
+                let converted = balance.convert(home, rate);
+                match balance.currency == *home {
+                    true => println!("- {}: {}", account.name, balance),
+                    false => println!("- {}: {} ({})", account.name, balance, converted),
+                }
+                total = total
+                    .checked_add(&converted)
+                    .ok_or_else(|| anyhow!("balance total overflowed"))?;
+            }
+            None => {
+                println!(
+                    "- {}: {} (no exchange rate to {})",
+                    account.name, balance, home
+                );
+                unconverted.push(balance.currency);
+            }
+        }
+    }
+
+    println!("Total: {}", total);
+    if !unconverted.is_empty() {
+        println!(
+            "Excludes balances in {}: import exchange rates with `money rates import`",
+            unconverted.join(", ")
+        );
     }
+
     Ok(())
 }
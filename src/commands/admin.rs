@@ -3,13 +3,16 @@ Command Line Interface `Admin` commands
 
 */
 
-use crate::config::Config;
+use super::{new_passphrase, token_key};
+use crate::config::{Backend, Config, TlsMode, TokenKeyConfig};
+use crate::crypto::{self, TokenKey};
 use crate::db::{self};
-use anyhow::Result;
+use crate::starling::client::StarlingApiClient;
+use anyhow::{anyhow, Result};
 use colored::Colorize;
 use sea_orm::Database;
 use std::io::Write;
-use std::{io, process};
+use std::{fs, io, process};
 
 /// Initialise the application.
 ///
@@ -54,8 +57,70 @@ pub async fn initialise() -> Result<()> {
 
 /// Add accounts from token
 ///
+/// The token is stored encrypted with the key from the config file.
 pub async fn add_account(token: &String) -> Result<()> {
-    db::account::add(token).await?;
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let client = StarlingApiClient::from_config(token, &config.starling);
+    db::account::add(&db, &client, &key.encrypt(token)).await?;
+
+    Ok(())
+}
+
+/// Re-encrypt every stored access token with a new key: a new random key written to `key_file`,
+/// which must not exist yet, or else a key derived from a new passphrase.
+///
+/// The old key must decrypt every token before the new key is made. The config file recording the
+/// new key is saved before the re-encrypted tokens are committed, and restored if the commit fails.
+pub async fn rotate_key(key_file: Option<&String>) -> Result<()> {
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let old_key = token_key(&mut config, &db).await?;
+    db::account::check_key(&db, &old_key).await?;
+
+    let (new_key, new_config) = match key_file {
+        Some(path) => {
+            let key = TokenKey::generate_key_file(path)?;
+            let new_config = TokenKeyConfig {
+                file: Some(path.to_owned()),
+                salt: None,
+                check: Some(key.key_check()),
+            };
+            (key, new_config)
+        }
+        None => {
+            let salt = crypto::generate_salt();
+            let key = TokenKey::from_passphrase(&new_passphrase()?, &salt)?;
+            let new_config = TokenKeyConfig {
+                file: None,
+                salt: Some(salt),
+                check: Some(key.key_check()),
+            };
+            (key, new_config)
+        }
+    };
+
+    let old_config = config.token_key.clone();
+    let rotated = db::account::rotate_key(&db, &old_key, &new_key, || {
+        config.token_key = new_config;
+        Ok(config.save()?)
+    })
+    .await;
+    let count = match rotated {
+        Ok(count) => count,
+        Err(e) => {
+            if config.token_key != old_config {
+                config.token_key = old_config;
+                config.save()?;
+            }
+            if let Some(path) = key_file {
+                let _ = fs::remove_file(path);
+            }
+            return Err(e);
+        }
+    };
+    println!("{}: re-encrypted {} token(s)", "OK".green(), count);
 
     Ok(())
 }
@@ -72,20 +137,64 @@ fn proceed() -> bool {
     io::stdin()
         .read_line(&mut response)
         .expect("failed to read response");
-    response.trim().to_lowercase() == String::from("y")
+    response.trim().to_lowercase() == "y"
 }
 
 // Get the database credentials from the user
 async fn get_db_credentials() -> Result<()> {
     let mut config = Config::new();
 
-    config.db.name = get_string("database name");
-    config.db.user = get_string("database user");
-    config.db.password = get_string("database password");
+    config.db.backend =
+        match get_string("database backend (mysql/postgres/sqlite) [mysql]").as_str() {
+            "" | "mysql" => Backend::Mysql,
+            "postgres" => Backend::Postgres,
+            "sqlite" => Backend::Sqlite,
+            other => return Err(anyhow!("unsupported database backend `{}`", other)),
+        };
+
+    match config.db.backend {
+        Backend::Mysql | Backend::Postgres => {
+            let host = get_string("database host [localhost]");
+            config.db.host = (!host.is_empty()).then_some(host);
+            let port = get_string(&format!(
+                "database port [{}]",
+                config.db.backend.default_port().unwrap_or_default()
+            ));
+            config.db.port = match port.as_str() {
+                "" => None,
+                port => Some(
+                    port.parse()
+                        .map_err(|_| anyhow!("invalid value for `db.port`: `{}`", port))?,
+                ),
+            };
+            config.db.name = get_string("database name");
+            config.db.user = get_string("database user");
+            config.db.password = get_string("database password");
+            let mode = get_string(
+                "TLS mode (preferred/disabled/required/verify-ca/verify-identity) [preferred]",
+            );
+            if !mode.is_empty() {
+                config.db.tls.mode = mode
+                    .parse()
+                    .map_err(|_| anyhow!("invalid value for `db.tls.mode`: `{}`", mode))?;
+            }
+            if matches!(
+                config.db.tls.mode,
+                TlsMode::VerifyCa | TlsMode::VerifyIdentity
+            ) {
+                config.db.tls.ca_cert = Some(get_string("CA certificate file"));
+            }
+        }
+        Backend::Sqlite => {
+            let path = get_string("database file [money.sqlite]");
+            config.db.path = (!path.is_empty()).then_some(path);
+        }
+    }
 
-    Database::connect(&config.db_url()).await?;
+    config.db.validate()?;
+    Database::connect(&config.db_url()?).await?;
 
-    config.save();
+    config.save()?;
 
     Ok(())
 }
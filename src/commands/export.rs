@@ -0,0 +1,122 @@
+/*!
+Command Line Interface `Export` commands
+
+*/
+
+use super::start_of_day;
+use crate::db;
+use crate::export::{BankDetails, ExportRecord, Format};
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+
+/// Write stored transactions between `from` and `to` (inclusive) in the given format, optionally
+/// only those in `category`: a space name or a category UID.
+///
+/// The journal is written to `output` if given, otherwise to stdout.
+pub async fn export(
+    format: Format,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    category: Option<&String>,
+    output: Option<&String>,
+) -> Result<()> {
+    let records = records(from, to, category).await?;
+    let exporter = format.exporter();
+
+    match output {
+        Some(filename) => {
+            let mut file = io::BufWriter::new(fs::File::create(filename)?);
+            exporter.write(&records, &mut file)?;
+            file.flush()?;
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut handle = stdout.lock();
+            exporter.write(&records, &mut handle)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the stored transactions in the date range and category with their account, space and
+/// counterparty names, the account and counterparty in the same query
+async fn records(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    category: Option<&String>,
+) -> Result<Vec<ExportRecord>> {
+    let from = from.map(start_of_day);
+    let to = to.map(|date| start_of_day(date) + Duration::days(1));
+    let db = db::get_database().await?;
+
+    let spaces = db::space::list(&db).await?;
+    let categories: Option<Vec<String>> = category.map(|category| {
+        let named: Vec<String> = spaces
+            .iter()
+            .filter(|space| space.name.eq_ignore_ascii_case(category))
+            .map(|space| space.uid.to_owned())
+            .collect();
+        match named.is_empty() {
+            true => vec![category.to_owned()],
+            false => named,
+        }
+    });
+    // transfers between these categories, an account's default category and its spaces, are
+    // internal
+    let known_categories: HashSet<String> = db::account::list(&db)
+        .await?
+        .into_iter()
+        .map(|account| account.default_category)
+        .chain(spaces.iter().map(|space| space.uid.to_owned()))
+        .collect();
+    let spaces: HashMap<String, String> = spaces
+        .into_iter()
+        .map(|space| (space.uid, space.name))
+        .collect();
+
+    let records = db::transaction::list_with_details(&db, from, to, categories.as_deref())
+        .await?
+        .into_iter()
+        .map(|detail| {
+            let transaction = detail.transaction;
+            let (account_name, account_created_at, bank_details) = match detail.account {
+                Some(account) => (
+                    account.name.to_owned(),
+                    account.created_at,
+                    BankDetails::from_account(&account),
+                ),
+                None => (
+                    String::new(),
+                    transaction.transaction_time,
+                    BankDetails::default(),
+                ),
+            };
+            let counterparty_category_uid = detail
+                .counterparty
+                .as_ref()
+                .filter(|counterparty| {
+                    counterparty.r#type == "CATEGORY"
+                        && known_categories.contains(&counterparty.uid)
+                })
+                .map(|counterparty| counterparty.uid.to_owned());
+            ExportRecord {
+                account_name,
+                account_created_at,
+                space_name: spaces.get(&transaction.category_uid).cloned(),
+                counterparty_name: detail
+                    .counterparty
+                    .map(|counterparty| counterparty.name)
+                    .unwrap_or_default(),
+                counterparty_category_uid,
+                bank_details,
+                transaction,
+            }
+        })
+        .collect();
+
+    Ok(records)
+}
@@ -1,3 +1,106 @@
 pub mod account;
 pub mod admin;
+pub mod export;
+pub mod rates;
+pub mod spaces;
+pub mod token;
 pub mod transactions;
+
+use crate::config::Config;
+use crate::crypto::{self, TokenKey};
+use crate::starling::client::StarlingApiClient;
+use crate::{db, entities};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::DatabaseConnection;
+
+/// The first instant of the given date, in UTC
+pub(crate) fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    DateTime::from_utc(date.and_hms_opt(0, 0, 0).unwrap(), Utc)
+}
+
+/// Return a function that builds an API client for a stored account, using the config file settings
+/// and decrypting the account's token with `key`
+pub(crate) fn api_client<'a>(
+    config: &'a Config,
+    key: &'a TokenKey,
+) -> impl Fn(&entities::account::Model) -> Result<StarlingApiClient> + 'a {
+    move |account| {
+        let client = StarlingApiClient::from_stored_token(&account.token, key, &config.starling)?;
+        Ok(client)
+    }
+}
+
+/// The key that encrypts stored access tokens: read from the config's key file if it has one,
+/// else derived from the passphrase in `MONEY_PASSPHRASE` or typed at a prompt.
+///
+/// The passphrase salt is generated the first time it's needed, when the passphrase is asked for
+/// twice. The key is checked against the key check in the config file or, before there is one,
+/// against a stored token, and refused if it doesn't match; the key check is then saved with the
+/// salt. Any tokens stored before encryption was added are encrypted with the key.
+pub(crate) async fn token_key(config: &mut Config, db: &DatabaseConnection) -> Result<TokenKey> {
+    let key = match (&config.token_key.file, &config.token_key.salt) {
+        (Some(path), _) => TokenKey::from_key_file(path)?,
+        (None, Some(salt)) => {
+            let passphrase = read_passphrase(
+                crypto::PASSPHRASE_ENV,
+                "Passphrase for the stored access tokens",
+                false,
+            )?;
+            TokenKey::from_passphrase(&passphrase, salt)?
+        }
+        (None, None) => {
+            let passphrase = read_passphrase(
+                crypto::PASSPHRASE_ENV,
+                "New passphrase for the stored access tokens",
+                true,
+            )?;
+            let salt = crypto::generate_salt();
+            let key = TokenKey::from_passphrase(&passphrase, &salt)?;
+            config.token_key.salt = Some(salt);
+            key
+        }
+    };
+
+    match &config.token_key.check {
+        Some(key_check) => key.check(key_check)?,
+        None => {
+            db::account::check_key(db, &key).await?;
+            config.token_key.check = Some(key.key_check());
+            config.save()?;
+        }
+    }
+
+    let encrypted = db::account::encrypt_plaintext_tokens(db, &key).await?;
+    if encrypted > 0 {
+        println!("Encrypted {} access token(s) stored unencrypted", encrypted);
+    }
+
+    Ok(key)
+}
+
+/// A new passphrase from `MONEY_NEW_PASSPHRASE`, or typed twice at a prompt
+pub(crate) fn new_passphrase() -> Result<String> {
+    read_passphrase(crypto::NEW_PASSPHRASE_ENV, "New passphrase", true)
+}
+
+// A passphrase from the environment variable `env`, or typed at a prompt; with `confirm`, typed
+// twice and refused if empty
+fn read_passphrase(env: &str, prompt: &str, confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(env) {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password(format!("{}: ", prompt))?;
+    if confirm {
+        if passphrase.is_empty() {
+            return Err(anyhow!("the passphrase must not be empty"));
+        }
+        let repeated =
+            rpassword::prompt_password(format!("Repeat the {}: ", prompt.to_lowercase()))?;
+        if repeated != passphrase {
+            return Err(anyhow!("the passphrases don't match"));
+        }
+    }
+
+    Ok(passphrase)
+}
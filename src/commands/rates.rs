@@ -0,0 +1,17 @@
+//! Command Line Interface `Rates` commands
+//!
+
+use crate::db;
+use anyhow::{Context, Result};
+use std::fs;
+
+/// Import exchange rates from a `date,from,to,rate` CSV file
+pub async fn import(filename: &String) -> Result<()> {
+    let csv = fs::read_to_string(filename).with_context(|| format!("reading `{}`", filename))?;
+    let rates = db::exchange_rate::parse_csv(&csv)?;
+    let db = db::get_database().await?;
+    db::exchange_rate::import(&db, &rates).await?;
+    println!("Imported {} exchange rate(s)", rates.len());
+
+    Ok(())
+}
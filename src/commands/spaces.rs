@@ -0,0 +1,45 @@
+//! Command Line Interface `Spaces` commands
+//!
+
+use super::{api_client, token_key};
+use crate::config::Config;
+use crate::db;
+use crate::starling::client::StarlingClient;
+use anyhow::Result;
+
+pub async fn list() -> Result<()> {
+    println!("Space list:");
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let client_for = api_client(&config, &key);
+    for account in db::account::list(&db).await? {
+        let client = client_for(&account)?;
+        for space in db::space::refresh(&db, &account.uid, &client).await? {
+            println!("- {} / {} ({})", account.name, space.name, space.kind);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn balance() -> Result<()> {
+    println!("Space balances:");
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let client_for = api_client(&config, &key);
+    for account in db::account::list(&db).await? {
+        let client = client_for(&account)?;
+        for space in client.spaces(&account.uid).await?.all() {
+            println!(
+                "- {} / {}: {}",
+                account.name,
+                space.name,
+                space.balance.as_string()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Command Line Interface `Token` commands
+//!
+//! Accounts added from the same access token share it, so tokens are shown with the accounts
+//! that use them, and picked by naming one of those accounts.
+
+use super::token_key;
+use crate::config::Config;
+use crate::crypto::TokenKey;
+use crate::db;
+use crate::entities::account;
+use crate::starling::client::{StarlingApiClient, StarlingClient};
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use sea_orm::DatabaseConnection;
+
+/// List the stored access tokens, masked, with the accounts that use each
+pub async fn list() -> Result<()> {
+    println!("Token list:");
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    for (token, accounts) in tokens(&db, &key).await? {
+        println!("- {}: {}", mask(&token), names(&accounts));
+    }
+
+    Ok(())
+}
+
+/// Check each stored token, or only the one `account` uses, with the Starling API, and show its
+/// scopes and expiry
+pub async fn verify(account: Option<&String>) -> Result<()> {
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let tokens = match account {
+        Some(account) => vec![token_for(&db, &key, account).await?],
+        None => tokens(&db, &key).await?,
+    };
+
+    for (token, accounts) in tokens {
+        let client = StarlingApiClient::from_config(&token, &config.starling);
+        print!("- {} ({}): ", mask(&token), names(&accounts));
+        match client.token_identity().await {
+            Ok(identity) => {
+                let expiry = match identity.expires_at {
+                    Some(expires_at) => format!("expires {}", expires_at.format("%Y-%m-%d %H:%M")),
+                    None => String::from("doesn't expire"),
+                };
+                println!("{}, {}", "OK".green(), expiry);
+                println!("  scopes: {}", identity.scopes.join(", "));
+                if let Err(missing) = identity.check_all_scopes() {
+                    println!("  {}: {}", "WARNING".yellow(), missing);
+                }
+            }
+            Err(e) => println!("{}: {}", "ERROR".red(), e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Store a new access token for every stored account it can see, e.g. to replace an expired token
+/// of the same account holder
+pub async fn replace(token: &String) -> Result<()> {
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let client = StarlingApiClient::from_config(token, &config.starling);
+    let account_uids: Vec<String> = client
+        .accounts()
+        .await?
+        .into_iter()
+        .map(|account| account.uid)
+        .collect();
+
+    let count = db::account::replace_token(&db, &account_uids, &key.encrypt(token)).await?;
+    if count == 0 {
+        return Err(anyhow!(
+            "the token's accounts aren't stored: add them with `money admin add`"
+        ));
+    }
+    println!(
+        "{}: replaced the token of {} account(s)",
+        "OK".green(),
+        count
+    );
+
+    Ok(())
+}
+
+/// Remove the token `account` uses, deleting every account that uses it and, if `transactions`,
+/// their stored transactions
+pub async fn remove(account: &str, transactions: bool) -> Result<()> {
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    let (_, accounts) = token_for(&db, &key, account).await?;
+    let account_uids: Vec<String> = accounts.iter().map(|a| a.uid.to_owned()).collect();
+
+    let count = db::transaction::count_for_accounts(&db, &account_uids).await?;
+    if count > 0 && !transactions {
+        return Err(anyhow!(
+            "{} has {} stored transaction(s): pass --transactions to delete them too",
+            names(&accounts),
+            count
+        ));
+    }
+    let deleted = db::account::remove(&db, &account_uids, transactions).await?;
+    println!(
+        "{}: removed {} and {} transaction(s)",
+        "OK".green(),
+        names(&accounts),
+        deleted
+    );
+
+    Ok(())
+}
+
+// The stored tokens, decrypted, each with the accounts that use it, in the order they were added
+async fn tokens(
+    db: &DatabaseConnection,
+    key: &TokenKey,
+) -> Result<Vec<(String, Vec<account::Model>)>> {
+    let mut tokens: Vec<(String, Vec<account::Model>)> = Vec::new();
+    for account in db::account::list(db).await? {
+        let token = key.decrypt(&account.token)?;
+        match tokens.iter_mut().find(|(stored, _)| *stored == token) {
+            Some((_, accounts)) => accounts.push(account),
+            None => tokens.push((token, vec![account])),
+        }
+    }
+
+    Ok(tokens)
+}
+
+// The token used by the account with the given name or UID
+async fn token_for(
+    db: &DatabaseConnection,
+    key: &TokenKey,
+    account: &str,
+) -> Result<(String, Vec<account::Model>)> {
+    let found = db::account::find(db, account)
+        .await?
+        .ok_or_else(|| anyhow!("no account `{}`", account))?;
+
+    tokens(db, key)
+        .await?
+        .into_iter()
+        .find(|(_, accounts)| accounts.iter().any(|a| a.uid == found.uid))
+        .ok_or_else(|| anyhow!("no token for account `{}`", account))
+}
+
+// Show only the ends of a token
+fn mask(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    match chars.len() {
+        0..=11 => String::from("****"),
+        n => format!(
+            "{}…{}",
+            chars[..4].iter().collect::<String>(),
+            chars[n - 4..].iter().collect::<String>()
+        ),
+    }
+}
+
+// The accounts' names, comma separated
+fn names(accounts: &[account::Model]) -> String {
+    accounts
+        .iter()
+        .map(|account| account.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -3,12 +3,45 @@ Command Line Interface `Transaction` commands
 
 */
 
+use super::{api_client, start_of_day, token_key};
+use crate::config::Config;
 use crate::db;
 use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
 
 /// Fetch transactions for the specified number of days and save to the database
 pub async fn update(days: i64) -> Result<()> {
-    db::transaction::insert_or_update(days).await?;
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    db::transaction::insert_or_update(&db, days, api_client(&config, &key)).await?;
+
+    Ok(())
+}
+
+/// Fetch transactions changed since the last sync (or all of them, if `full`) and save to the
+/// database
+pub async fn sync(full: bool) -> Result<()> {
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    db::transaction::sync(&db, full, api_client(&config, &key)).await?;
+
+    Ok(())
+}
+
+/// Fetch settled transactions between `from` and `to` (inclusive, default today) and save to the
+/// database
+pub async fn update_between(from: NaiveDate, to: Option<NaiveDate>) -> Result<()> {
+    let from = start_of_day(from);
+    let to = match to {
+        Some(to) => start_of_day(to) + Duration::days(1),
+        None => Utc::now(),
+    };
+    let db = db::get_database().await?;
+    let mut config = Config::new();
+    let key = token_key(&mut config, &db).await?;
+    db::transaction::insert_or_update_between(&db, from, to, api_client(&config, &key)).await?;
 
     Ok(())
 }
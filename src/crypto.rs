@@ -0,0 +1,212 @@
+//! Encryption of the Starling access tokens stored in the database
+//!
+//! Tokens are encrypted with AES-256-GCM. The key is either read from a local key file, or
+//! derived from a passphrase with Argon2 and a salt kept in the config file. A stored token has
+//! the form `enc:v1:<base64 of nonce and ciphertext>`; anything else is a token stored before
+//! encryption was added, and is encrypted the first time the key is used.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::RngCore;
+use std::fs;
+use std::io::Write;
+use thiserror::Error;
+
+/// Environment variable holding the passphrase, instead of prompting for it
+pub const PASSPHRASE_ENV: &str = "MONEY_PASSPHRASE";
+
+/// Environment variable holding the new passphrase for `money admin rotate-key`
+pub const NEW_PASSPHRASE_ENV: &str = "MONEY_NEW_PASSPHRASE";
+
+const PREFIX: &str = "enc:v1:";
+// The text encrypted as a key check, so a wrong key can be recognised
+const CHECK_TEXT: &str = "money token key check";
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const SALT_LENGTH: usize = 16;
+
+/// Represents a failure to encrypt or decrypt an access token
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("could not decrypt the access token: the key is wrong or the token is corrupt")]
+    Decrypt,
+
+    #[error("invalid key file `{path}`: {reason}")]
+    KeyFile { path: String, reason: String },
+
+    #[error("invalid key salt in the config file")]
+    Salt,
+
+    #[error("could not derive a key from the passphrase: {0}")]
+    Passphrase(String),
+
+    #[error("the passphrase or key file doesn't match the key the stored access tokens are encrypted with")]
+    WrongKey,
+}
+
+/// Represents the key that encrypts the stored access tokens
+pub struct TokenKey {
+    cipher: Aes256Gcm,
+}
+
+impl TokenKey {
+    /// Derive a key from a passphrase and a salt made by `generate_salt`
+    pub fn from_passphrase(passphrase: &str, salt: &str) -> Result<Self, CryptoError> {
+        let salt = BASE64.decode(salt).map_err(|_| CryptoError::Salt)?;
+        let mut key = [0u8; KEY_LENGTH];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| CryptoError::Passphrase(e.to_string()))?;
+
+        Ok(Self::from_bytes(&key))
+    }
+
+    /// Read a key written by `generate_key_file`
+    pub fn from_key_file(path: &str) -> Result<Self, CryptoError> {
+        let invalid = |reason: &str| CryptoError::KeyFile {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        };
+        let contents = fs::read_to_string(path).map_err(|e| invalid(&e.to_string()))?;
+        let key = BASE64
+            .decode(contents.trim())
+            .map_err(|_| invalid("not base64"))?;
+        if key.len() != KEY_LENGTH {
+            return Err(invalid("expected a 256-bit key"));
+        }
+
+        Ok(Self::from_bytes(&key))
+    }
+
+    /// Write a new random key to a file that doesn't exist yet, readable only by its owner
+    pub fn generate_key_file(path: &str) -> Result<Self, CryptoError> {
+        let mut key = [0u8; KEY_LENGTH];
+        OsRng.fill_bytes(&mut key);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", BASE64.encode(key)))
+            .map_err(|e| CryptoError::KeyFile {
+                path: path.to_string(),
+                reason: e.to_string(),
+            })?;
+
+        Ok(Self::from_bytes(&key))
+    }
+
+    fn from_bytes(key: &[u8]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new_from_slice(key).expect("a 256-bit key"),
+        }
+    }
+
+    /// Encrypt an access token for storing
+    pub fn encrypt(&self, token: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, token.as_bytes())
+            .expect("encrypting a token");
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        format!("{}{}", PREFIX, BASE64.encode(payload))
+    }
+
+    /// Decrypt a stored access token, or return it as it is if it was stored unencrypted
+    pub fn decrypt(&self, stored: &str) -> Result<String, CryptoError> {
+        let Some(encoded) = stored.strip_prefix(PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let payload = BASE64.decode(encoded).map_err(|_| CryptoError::Decrypt)?;
+        if payload.len() < NONCE_LENGTH {
+            return Err(CryptoError::Decrypt);
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+        let token = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| CryptoError::Decrypt)?;
+
+        String::from_utf8(token).map_err(|_| CryptoError::Decrypt)
+    }
+
+    /// A known value encrypted with this key, for `check` to recognise the key by
+    pub fn key_check(&self) -> String {
+        self.encrypt(CHECK_TEXT)
+    }
+
+    /// Return an error unless `key_check` was made by this key
+    pub fn check(&self, key_check: &str) -> Result<(), CryptoError> {
+        match is_encrypted(key_check) && self.decrypt(key_check).ok().as_deref() == Some(CHECK_TEXT)
+        {
+            true => Ok(()),
+            false => Err(CryptoError::WrongKey),
+        }
+    }
+}
+
+/// Return true if a stored access token is encrypted
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(PREFIX)
+}
+
+/// A new random salt for `TokenKey::from_passphrase`
+pub fn generate_salt() -> String {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    BASE64.encode(salt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encrypts_and_decrypts_a_token() {
+        let salt = generate_salt();
+        let key = TokenKey::from_passphrase("correct horse", &salt).unwrap();
+
+        let stored = key.encrypt("token");
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("token"));
+        assert_ne!(stored, key.encrypt("token"));
+        assert_eq!(key.decrypt(&stored).unwrap(), "token");
+
+        // unencrypted tokens are passed through
+        assert_eq!(key.decrypt("plain").unwrap(), "plain");
+
+        let wrong = TokenKey::from_passphrase("battery staple", &salt).unwrap();
+        assert!(matches!(wrong.decrypt(&stored), Err(CryptoError::Decrypt)));
+
+        let key_check = key.key_check();
+        assert!(key.check(&key_check).is_ok());
+        assert!(matches!(
+            wrong.check(&key_check),
+            Err(CryptoError::WrongKey)
+        ));
+    }
+
+    #[test]
+    fn it_reads_a_generated_key_file() {
+        let path =
+            std::env::temp_dir().join(format!("money-key-{}", generate_salt().replace('/', "_")));
+        let path = path.to_str().unwrap();
+
+        let key = TokenKey::generate_key_file(path).unwrap();
+        let stored = key.encrypt("token");
+        let read = TokenKey::from_key_file(path).unwrap();
+        assert_eq!(read.decrypt(&stored).unwrap(), "token");
+
+        // an existing key is never overwritten
+        assert!(TokenKey::generate_key_file(path).is_err());
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,115 @@
+//! ISO 4217 currencies and the number of decimal places of their minor units
+//!
+//! Amounts are held in minor units, so converting to and from major units needs each currency's
+//! exponent: 2 for GBP (pence), 0 for JPY, 3 for KWD (fils).
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The exponent assumed for a currency code that isn't in ISO 4217
+pub const DEFAULT_EXPONENT: u32 = 2;
+
+// Define `Currency` with a variant per code, and the exponent of each
+macro_rules! currencies {
+    ($($code:ident => $exponent:literal,)*) => {
+        /// Represents an active ISO 4217 currency
+        ///
+        /// Parsing only accepts ISO 4217 codes, but any other code is deserialised as `Other`.
+        #[derive(
+            Serialize,
+            Deserialize,
+            Debug,
+            Clone,
+            PartialEq,
+            Eq,
+            Hash,
+            strum_macros::EnumString,
+        )]
+        pub enum Currency {
+            $($code,)*
+            /// A code that isn't in ISO 4217
+            #[serde(untagged)]
+            #[strum(disabled)]
+            Other(String),
+        }
+
+        impl Currency {
+            /// The number of decimal places in a major unit, e.g. 2 for GBP
+            pub fn exponent(&self) -> u32 {
+                match self {
+                    $(Currency::$code => $exponent,)*
+                    Currency::Other(_) => DEFAULT_EXPONENT,
+                }
+            }
+
+            /// The currency code, e.g. `GBP`
+            pub fn code(&self) -> &str {
+                match self {
+                    $(Currency::$code => stringify!($code),)*
+                    Currency::Other(code) => code,
+                }
+            }
+        }
+
+        impl fmt::Display for Currency {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.pad(self.code())
+            }
+        }
+    };
+}
+
+currencies! {
+    AED => 2, AFN => 2, ALL => 2, AMD => 2, ANG => 2, AOA => 2, ARS => 2, AUD => 2, AWG => 2,
+    AZN => 2, BAM => 2, BBD => 2, BDT => 2, BGN => 2, BHD => 3, BIF => 0, BMD => 2, BND => 2,
+    BOB => 2, BOV => 2, BRL => 2, BSD => 2, BTN => 2, BWP => 2, BYN => 2, BZD => 2, CAD => 2,
+    CDF => 2, CHE => 2, CHF => 2, CHW => 2, CLF => 4, CLP => 0, CNY => 2, COP => 2, COU => 2,
+    CRC => 2, CUC => 2, CUP => 2, CVE => 2, CZK => 2, DJF => 0, DKK => 2, DOP => 2, DZD => 2,
+    EGP => 2, ERN => 2, ETB => 2, EUR => 2, FJD => 2, FKP => 2, GBP => 2, GEL => 2, GHS => 2,
+    GIP => 2, GMD => 2, GNF => 0, GTQ => 2, GYD => 2, HKD => 2, HNL => 2, HTG => 2, HUF => 2,
+    IDR => 2, ILS => 2, INR => 2, IQD => 3, IRR => 2, ISK => 0, JMD => 2, JOD => 3, JPY => 0,
+    KES => 2, KGS => 2, KHR => 2, KMF => 0, KPW => 2, KRW => 0, KWD => 3, KYD => 2, KZT => 2,
+    LAK => 2, LBP => 2, LKR => 2, LRD => 2, LSL => 2, LYD => 3, MAD => 2, MDL => 2, MGA => 2,
+    MKD => 2, MMK => 2, MNT => 2, MOP => 2, MRU => 2, MUR => 2, MVR => 2, MWK => 2, MXN => 2,
+    MXV => 2, MYR => 2, MZN => 2, NAD => 2, NGN => 2, NIO => 2, NOK => 2, NPR => 2, NZD => 2,
+    OMR => 3, PAB => 2, PEN => 2, PGK => 2, PHP => 2, PKR => 2, PLN => 2, PYG => 0, QAR => 2,
+    RON => 2, RSD => 2, RUB => 2, RWF => 0, SAR => 2, SBD => 2, SCR => 2, SDG => 2, SEK => 2,
+    SGD => 2, SHP => 2, SLE => 2, SLL => 2, SOS => 2, SRD => 2, SSP => 2, STN => 2, SVC => 2,
+    SYP => 2, SZL => 2, THB => 2, TJS => 2, TMT => 2, TND => 3, TOP => 2, TRY => 2, TTD => 2,
+    TWD => 2, TZS => 2, UAH => 2, UGX => 0, USD => 2, USN => 2, UYI => 0, UYU => 2, UYW => 4,
+    UZS => 2, VED => 2, VES => 2, VND => 0, VUV => 0, WST => 2, XAF => 0, XCD => 2, XOF => 0,
+    XPF => 0, YER => 2, ZAR => 2, ZMW => 2, ZWL => 2,
+}
+
+/// The exponent of a currency code, or `DEFAULT_EXPONENT` if it isn't in ISO 4217
+pub fn exponent(code: &str) -> u32 {
+    code.parse::<Currency>()
+        .map(|currency| currency.exponent())
+        .unwrap_or(DEFAULT_EXPONENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_codes_with_their_exponents() {
+        assert_eq!("EUR".parse::<Currency>().unwrap(), Currency::EUR);
+        assert_eq!(Currency::JPY.to_string(), "JPY");
+        assert_eq!(exponent("GBP"), 2);
+        assert_eq!(exponent("JPY"), 0);
+        assert_eq!(exponent("KWD"), 3);
+        assert_eq!(exponent("XYZ"), DEFAULT_EXPONENT);
+    }
+
+    #[test]
+    fn it_deserialises_codes() {
+        let currency: Currency = serde_json::from_str(r#""CHF""#).unwrap();
+        assert_eq!(currency, Currency::CHF);
+
+        let currency: Currency = serde_json::from_str(r#""XBT""#).unwrap();
+        assert_eq!(currency, Currency::Other(String::from("XBT")));
+        assert_eq!(currency.exponent(), DEFAULT_EXPONENT);
+        assert!("XBT".parse::<Currency>().is_err());
+    }
+}
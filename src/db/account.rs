@@ -1,43 +1,261 @@
 //! Functions for interacting with table `accounts`
 
-use super::get_database;
-
-use crate::entities::{account, prelude::*};
+use crate::crypto::{self, TokenKey};
+use crate::db;
+use crate::entities::{account, prelude::*, space, sync_state, transaction};
 use crate::starling::account::Account as StarlingAccount;
-use crate::starling::client::{StarlingApiClient, StarlingClient};
-use anyhow::Result;
+use crate::starling::client::StarlingClient;
+use crate::starling::identity::ACCOUNT_DETAILS;
+use crate::starling::transaction::StarlingTransaction;
+use anyhow::{anyhow, Result};
 use sea_orm::*;
 
-/// Insert accounts for the Starling account type with the given access token
-pub async fn add(token: &String) -> Result<Vec<StarlingAccount>> {
-    let db = get_database().await.unwrap();
-    let client = StarlingApiClient::new(token);
-    let accounts = client.accounts().await;
+/// Insert the accounts the client can see, storing the access token it uses, which should already
+/// be encrypted, and the accounts' details if the token grants the scopes for them.
+///
+/// Nothing is stored if the token lacks a scope any required feature needs; the error names them
+/// all.
+pub async fn add(
+    db: &DatabaseConnection,
+    client: &impl StarlingClient,
+    token: &String,
+) -> Result<Vec<StarlingAccount>> {
+    let identity = client.token_identity().await?;
+    identity.check_scopes()?;
+    let accounts = client.accounts().await?;
 
     println!("Processing {} account(s)", accounts.len());
 
     for account in accounts.iter() {
-        insert_account(account, token, &db).await?;
+        insert_account(account, token, db).await?;
     }
 
+    let account_uids: Vec<String> = accounts.iter().map(|a| a.uid.to_owned()).collect();
+    refresh_details(db, client, &account_uids).await?;
+
     Ok(accounts)
 }
 
+/// Fetch and store the account holder, sort code, account number, IBAN and BIC of the given
+/// accounts, which the client must be able to see.
+///
+/// The scopes for them are optional, so a token without them is skipped with a message; returns
+/// whether the details were stored.
+pub async fn refresh_details(
+    db: &DatabaseConnection,
+    client: &impl StarlingClient,
+    account_uids: &[String],
+) -> Result<bool> {
+    if !client.token_identity().await?.grants(&ACCOUNT_DETAILS) {
+        println!(
+            "Skipping account details: the token lacks {}",
+            ACCOUNT_DETAILS.scopes.join(", ")
+        );
+        return Ok(false);
+    }
+
+    let holder = client.account_holder().await?;
+    let holder_name = client.account_holder_name().await?;
+
+    for account_uid in account_uids {
+        let identifiers = client.account_identifiers(account_uid).await?;
+        let Some(account) = account_exists(db, account_uid).await else {
+            continue;
+        };
+        let mut record: account::ActiveModel = account.into();
+        record.account_holder_uid = ActiveValue::set(Some(holder.uid.to_owned()));
+        record.account_holder_type = ActiveValue::set(Some(holder.holder_type.to_owned()));
+        record.account_holder_name = ActiveValue::set(Some(holder_name.to_owned()));
+        record.sort_code = ActiveValue::set(Some(identifiers.bank_identifier));
+        record.account_number = ActiveValue::set(Some(identifiers.account_identifier));
+        record.iban = ActiveValue::set(identifiers.iban);
+        record.bic = ActiveValue::set(identifiers.bic);
+        record.update(db).await?;
+    }
+
+    Ok(true)
+}
+
+/// Recognises transaction counterparties that are stored accounts, by sort code and account
+/// number, or by IBAN
+pub struct OwnAccounts {
+    accounts: Vec<account::Model>,
+}
+
+impl OwnAccounts {
+    pub async fn load(db: &impl ConnectionTrait) -> Result<Self> {
+        let accounts = Account::find().all(db).await?;
+
+        Ok(Self { accounts })
+    }
+
+    /// The UID of the stored account a feed item's counterparty is, if it is one
+    pub fn account_for(&self, item: &StarlingTransaction) -> Option<String> {
+        // a counterparty paid by IBAN may have no sort code
+        let identifier = item.counterparty_identifier.as_deref().map(normalise);
+        let sub_identifier = normalise(item.counterparty_sub_identifier.as_deref()?);
+
+        self.accounts
+            .iter()
+            .find(|account| {
+                let is = |value: &Option<String>, expected: &str| {
+                    value.as_deref().map(normalise).as_deref() == Some(expected)
+                };
+                let by_account_number = identifier.as_deref().is_some_and(|identifier| {
+                    is(&account.sort_code, identifier)
+                        && is(&account.account_number, &sub_identifier)
+                });
+                by_account_number || is(&account.iban, &sub_identifier)
+            })
+            .map(|account| account.uid.to_owned())
+    }
+}
+
+// An identifier without spaces or dashes, in upper case, e.g. `608371` for `60-83-71`
+fn normalise(identifier: &str) -> String {
+    identifier
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
 /// List accounts
-pub async fn list() -> Result<Vec<account::Model>> {
-    let db = get_database().await.unwrap();
-    let accounts: Vec<account::Model> = Account::find().all(&db).await?;
+pub async fn list(db: &DatabaseConnection) -> Result<Vec<account::Model>> {
+    let accounts: Vec<account::Model> = Account::find().all(db).await?;
 
     Ok(accounts)
 }
 
+/// Find an account by its name, ignoring case, or its UID
+pub async fn find(db: &DatabaseConnection, name_or_uid: &str) -> Result<Option<account::Model>> {
+    let account = list(db).await?.into_iter().find(|account| {
+        account.uid == name_or_uid || account.name.eq_ignore_ascii_case(name_or_uid)
+    });
+
+    Ok(account)
+}
+
+/// Store a new access token, already encrypted, for the given accounts, returning the number of
+/// accounts updated
+pub async fn replace_token(
+    db: &DatabaseConnection,
+    account_uids: &[String],
+    token: &str,
+) -> Result<u64> {
+    let result = Account::update_many()
+        .col_expr(account::Column::Token, token.into())
+        .filter(account::Column::Uid.is_in(account_uids.iter().cloned()))
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected)
+}
+
+/// Delete the given accounts with their spaces and sync marks, and their transactions if
+/// `with_transactions`, all in one database transaction.
+///
+/// Without `with_transactions` nothing is deleted if any of the accounts has stored transactions,
+/// as they would be left without an account.
+pub async fn remove(
+    db: &DatabaseConnection,
+    account_uids: &[String],
+    with_transactions: bool,
+) -> Result<u64> {
+    let uids = || account_uids.iter().cloned();
+    let txn = db.begin().await?;
+
+    let transactions =
+        Transaction::delete_many().filter(transaction::Column::AccountUid.is_in(uids()));
+    let deleted = match with_transactions {
+        true => transactions.exec(&txn).await?.rows_affected,
+        false => {
+            let count = db::transaction::count_for_accounts(&txn, account_uids).await?;
+            if count > 0 {
+                return Err(anyhow!("the accounts have {} stored transaction(s)", count));
+            }
+            0
+        }
+    };
+    SyncState::delete_many()
+        .filter(sync_state::Column::AccountUid.is_in(uids()))
+        .exec(&txn)
+        .await?;
+    Space::delete_many()
+        .filter(space::Column::AccountUid.is_in(uids()))
+        .exec(&txn)
+        .await?;
+    Account::delete_many()
+        .filter(account::Column::Uid.is_in(uids()))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+
+    Ok(deleted)
+}
+
+/// Return `CryptoError::WrongKey` if any stored access token is encrypted with a key other than
+/// `key`
+pub async fn check_key(db: &DatabaseConnection, key: &TokenKey) -> Result<()> {
+    for account in list(db).await? {
+        if crypto::is_encrypted(&account.token) {
+            key.decrypt(&account.token)
+                .map_err(|_| crypto::CryptoError::WrongKey)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Encrypt with `key` any access tokens stored before encryption was added, returning the number
+/// of accounts updated
+pub async fn encrypt_plaintext_tokens(db: &DatabaseConnection, key: &TokenKey) -> Result<usize> {
+    let plaintext: Vec<account::Model> = list(db)
+        .await?
+        .into_iter()
+        .filter(|account| !crypto::is_encrypted(&account.token))
+        .collect();
+    for account in plaintext.iter() {
+        let mut record: account::ActiveModel = account.to_owned().into();
+        record.token = ActiveValue::set(key.encrypt(&account.token));
+        record.update(db).await?;
+    }
+
+    Ok(plaintext.len())
+}
+
+/// Decrypt each stored access token with `old_key` and store it encrypted with `new_key`, all in
+/// one database transaction, returning the number of accounts updated.
+///
+/// `before_commit` runs once every token is re-encrypted, e.g. to record the new key, and the
+/// transaction is rolled back if it fails.
+pub async fn rotate_key(
+    db: &DatabaseConnection,
+    old_key: &TokenKey,
+    new_key: &TokenKey,
+    before_commit: impl FnOnce() -> Result<()>,
+) -> Result<usize> {
+    let txn = db.begin().await?;
+    let accounts = Account::find().all(&txn).await?;
+    for account in accounts.iter() {
+        let token = old_key.decrypt(&account.token)?;
+        let mut record: account::ActiveModel = account.to_owned().into();
+        record.token = ActiveValue::set(new_key.encrypt(&token));
+        record.update(&txn).await?;
+    }
+    before_commit()?;
+    txn.commit().await?;
+
+    Ok(accounts.len())
+}
+
 /// If the account doesn't exist in the database, insert it
 pub async fn insert_account(
     account: &StarlingAccount,
     token: &String,
     db: &DatabaseConnection,
 ) -> Result<(), anyhow::Error> {
-    match account_exists(&account.uid).await {
+    match account_exists(db, &account.uid).await {
         None => {
             let record = make_record(token, account);
             Account::insert(record).exec(db).await?;
@@ -60,11 +278,187 @@ fn make_record(token: &String, account: &StarlingAccount) -> account::ActiveMode
 }
 
 /// Return true if an account with the given account uid exists in the database.
-async fn account_exists(account_uid: &String) -> Option<account::Model> {
-    let db = get_database().await.unwrap();
+async fn account_exists(db: &DatabaseConnection, account_uid: &String) -> Option<account::Model> {
     Account::find()
         .filter(account::Column::Uid.eq(account_uid))
-        .one(&db)
+        .one(db)
         .await
-        .expect("getting counterparty id")
+        .expect("getting account")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::{fixture_client, test_database};
+    use crate::starling::identity::{MissingScopes, TokenIdentity};
+
+    #[tokio::test]
+    async fn it_adds_each_account_once() {
+        let db = test_database().await;
+        let client = fixture_client();
+        let token = String::from("token");
+
+        add(&db, &client, &token).await.unwrap();
+        add(&db, &client, &token).await.unwrap();
+
+        let accounts = list(&db).await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].name, "Personal");
+        assert_eq!(accounts[0].token, "token");
+        assert_eq!(accounts[0].account_holder_name.as_deref(), Some("Jane Doe"));
+        assert_eq!(accounts[0].sort_code.as_deref(), Some("608371"));
+        assert_eq!(accounts[0].account_number.as_deref(), Some("12345678"));
+        assert_eq!(accounts[0].iban.as_deref(), Some("GB00SRLG60837112345678"));
+    }
+
+    #[tokio::test]
+    async fn it_recognises_own_accounts_by_account_number_or_iban() {
+        let db = test_database().await;
+        let client = fixture_client();
+        add(&db, &client, &String::from("token")).await.unwrap();
+        let own_accounts = OwnAccounts::load(&db).await.unwrap();
+
+        let paying = |identifiers: &str| -> StarlingTransaction {
+            serde_json::from_str(&format!(
+                r#"{{
+                    "feedItemUid": "feed-uid",
+                    "amount": {{ "currency": "GBP", "minorUnits": 5000 }},
+                    "counterPartyName": "Personal",
+                    "counterPartyType": "PAYEE",
+                    {}
+                    "direction": "OUT",
+                    "spendingCategory": "TRANSFERS",
+                    "status": "SETTLED",
+                    "transactionTime": "2023-05-05T12:00:00.000Z"
+                }}"#,
+                identifiers
+            ))
+            .unwrap()
+        };
+        let personal = Some(client.accounts[0].uid.to_owned());
+
+        let by_account_number = paying(
+            r#""counterPartySubEntityIdentifier": "60-83-71",
+            "counterPartySubEntitySubIdentifier": "12345678","#,
+        );
+        assert_eq!(own_accounts.account_for(&by_account_number), personal);
+        let by_iban =
+            paying(r#""counterPartySubEntitySubIdentifier": "GB00 SRLG 6083 7112 3456 78","#);
+        assert_eq!(own_accounts.account_for(&by_iban), personal);
+        let elsewhere = paying(
+            r#""counterPartySubEntityIdentifier": "60-83-71",
+            "counterPartySubEntitySubIdentifier": "87654321","#,
+        );
+        assert_eq!(own_accounts.account_for(&elsewhere), None);
+    }
+
+    #[tokio::test]
+    async fn it_refuses_a_token_without_the_required_scopes() {
+        let db = test_database().await;
+        let mut identity = TokenIdentity::with_all_scopes("holder-uid");
+        identity.scopes.retain(|scope| scope != "transaction:read");
+        let client = fixture_client().with_identity(identity);
+
+        let error = add(&db, &client, &String::from("token")).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MissingScopes>().unwrap().features,
+            vec![(
+                String::from("transactions"),
+                vec![String::from("transaction:read")]
+            )]
+        );
+        assert!(list(&db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_skips_account_details_without_the_scopes_for_them() {
+        let db = test_database().await;
+        let mut identity = TokenIdentity::with_all_scopes("holder-uid");
+        identity
+            .scopes
+            .retain(|scope| scope != "account-identifier:read");
+        let client = fixture_client().with_identity(identity);
+
+        add(&db, &client, &String::from("token")).await.unwrap();
+        let account_uids = [client.accounts[0].uid.to_owned()];
+        assert!(!refresh_details(&db, &client, &account_uids).await.unwrap());
+
+        let accounts = list(&db).await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].account_holder_name, None);
+        assert_eq!(accounts[0].sort_code, None);
+    }
+
+    #[tokio::test]
+    async fn it_rotates_the_token_key() {
+        let db = test_database().await;
+        let client = fixture_client();
+        // a token stored before encryption was added
+        add(&db, &client, &String::from("token")).await.unwrap();
+
+        let old_key = TokenKey::from_passphrase("old", &crypto::generate_salt()).unwrap();
+        let new_key = TokenKey::from_passphrase("new", &crypto::generate_salt()).unwrap();
+        // nothing is changed if the new key can't be recorded
+        let error = rotate_key(&db, &old_key, &new_key, || Err(anyhow!("read-only")))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "read-only");
+        assert_eq!(list(&db).await.unwrap()[0].token, "token");
+
+        let rotated = rotate_key(&db, &old_key, &new_key, || Ok(())).await;
+        assert_eq!(rotated.unwrap(), 1);
+
+        let stored = list(&db).await.unwrap()[0].token.to_owned();
+        assert!(crypto::is_encrypted(&stored));
+        assert_eq!(new_key.decrypt(&stored).unwrap(), "token");
+        assert!(old_key.decrypt(&stored).is_err());
+
+        // a mistyped passphrase is recognised before anything is stored with it
+        assert!(check_key(&db, &new_key).await.is_ok());
+        let error = check_key(&db, &old_key).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<crypto::CryptoError>(),
+            Some(crypto::CryptoError::WrongKey)
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_encrypts_plaintext_tokens() {
+        let db = test_database().await;
+        let client = fixture_client();
+        add(&db, &client, &String::from("token")).await.unwrap();
+
+        let key = TokenKey::from_passphrase("key", &crypto::generate_salt()).unwrap();
+        assert_eq!(encrypt_plaintext_tokens(&db, &key).await.unwrap(), 1);
+        assert_eq!(encrypt_plaintext_tokens(&db, &key).await.unwrap(), 0);
+
+        let stored = list(&db).await.unwrap()[0].token.to_owned();
+        assert!(crypto::is_encrypted(&stored));
+        assert_eq!(key.decrypt(&stored).unwrap(), "token");
+    }
+
+    #[tokio::test]
+    async fn it_replaces_and_removes_tokens() {
+        let db = test_database().await;
+        let client = fixture_client();
+        add(&db, &client, &String::from("old")).await.unwrap();
+        db::transaction::sync(&db, false, |_| Ok(client.clone()))
+            .await
+            .unwrap();
+
+        let account = find(&db, "personal").await.unwrap().unwrap();
+        let uids = vec![account.uid.to_owned()];
+        assert_eq!(replace_token(&db, &uids, "new").await.unwrap(), 1);
+        assert_eq!(list(&db).await.unwrap()[0].token, "new");
+
+        // the account's transactions are only deleted when asked
+        assert!(remove(&db, &uids, false).await.is_err());
+        assert_eq!(list(&db).await.unwrap().len(), 1);
+        // and the foreign key keeps them from being left without their account
+        assert!(Account::delete_many().exec(&db).await.is_err());
+        assert_eq!(remove(&db, &uids, true).await.unwrap(), 4);
+        assert!(list(&db).await.unwrap().is_empty());
+        assert!(Space::find().all(&db).await.unwrap().is_empty());
+        assert!(SyncState::find().all(&db).await.unwrap().is_empty());
+    }
 }
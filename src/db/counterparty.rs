@@ -1,4 +1,12 @@
+//! Functions for interacting with table `counterparty`
 
+use crate::entities::{counterparty, prelude::*};
+use anyhow::Result;
+use sea_orm::*;
 
+/// List counterparties
+pub async fn list(db: &DatabaseConnection) -> Result<Vec<counterparty::Model>> {
+    let counterparties: Vec<counterparty::Model> = Counterparty::find().all(db).await?;
 
-
+    Ok(counterparties)
+}
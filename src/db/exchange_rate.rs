@@ -0,0 +1,172 @@
+//! Functions for interacting with table `exchange_rate`
+//!
+//! Rates are imported from a local CSV file with lines of the form `date,from,to,rate`, e.g.
+//! `2023-05-01,EUR,GBP,0.8712`, meaning one EUR bought 0.8712 GBP on that day.
+
+use crate::currency::Currency;
+use crate::entities::{exchange_rate, prelude::*};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use sea_orm::*;
+
+/// Represents an exchange rate to import
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rate {
+    pub date: NaiveDate,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64,
+}
+
+/// Parse `date,from,to,rate` lines, skipping blank lines, `#` comments and a header line
+pub fn parse_csv(csv: &str) -> Result<Vec<Rate>> {
+    let mut rates = Vec::new();
+    for (number, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if number == 0 && fields[0].parse::<NaiveDate>().is_err() {
+            continue;
+        }
+
+        let invalid = |reason: &str| anyhow!("line {}: {}: `{}`", number + 1, reason, line);
+        let [date, from, to, rate] = fields[..] else {
+            return Err(invalid("expected date,from,to,rate"));
+        };
+        for code in [from, to] {
+            code.parse::<Currency>()
+                .map_err(|_| invalid("unknown currency"))?;
+        }
+        let rate: f64 = rate.parse().map_err(|_| invalid("invalid rate"))?;
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(invalid("rate must be positive"));
+        }
+
+        rates.push(Rate {
+            date: date.parse().map_err(|_| invalid("invalid date"))?,
+            from_currency: from.to_string(),
+            to_currency: to.to_string(),
+            rate,
+        });
+    }
+
+    Ok(rates)
+}
+
+/// Insert the rates, replacing any already stored for the same day and currencies
+pub async fn import(db: &DatabaseConnection, rates: &[Rate]) -> Result<()> {
+    for rate in rates {
+        let existing = ExchangeRate::find()
+            .filter(exchange_rate::Column::Date.eq(rate.date))
+            .filter(exchange_rate::Column::FromCurrency.eq(rate.from_currency.as_str()))
+            .filter(exchange_rate::Column::ToCurrency.eq(rate.to_currency.as_str()))
+            .one(db)
+            .await?;
+
+        match existing {
+            Some(record) => {
+                let mut record: exchange_rate::ActiveModel = record.into();
+                record.rate = ActiveValue::set(rate.rate);
+                record.update(db).await?;
+            }
+            None => {
+                let record = exchange_rate::ActiveModel {
+                    date: ActiveValue::set(rate.date),
+                    from_currency: ActiveValue::set(rate.from_currency.to_owned()),
+                    to_currency: ActiveValue::set(rate.to_currency.to_owned()),
+                    rate: ActiveValue::set(rate.rate),
+                    ..Default::default()
+                };
+                ExchangeRate::insert(record).exec(db).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The latest rate from one currency to another on or before `date`, using the inverse of the
+/// opposite rate if that's all there is
+pub async fn rate_on(
+    db: &DatabaseConnection,
+    from_currency: &str,
+    to_currency: &str,
+    date: NaiveDate,
+) -> Result<Option<f64>> {
+    if from_currency == to_currency {
+        return Ok(Some(1.0));
+    }
+    if let Some(rate) = latest(db, from_currency, to_currency, date).await? {
+        return Ok(Some(rate.rate));
+    }
+    let inverse = latest(db, to_currency, from_currency, date).await?;
+
+    Ok(inverse.map(|rate| 1.0 / rate.rate))
+}
+
+async fn latest(
+    db: &DatabaseConnection,
+    from_currency: &str,
+    to_currency: &str,
+    date: NaiveDate,
+) -> Result<Option<exchange_rate::Model>> {
+    let rate = ExchangeRate::find()
+        .filter(exchange_rate::Column::FromCurrency.eq(from_currency))
+        .filter(exchange_rate::Column::ToCurrency.eq(to_currency))
+        .filter(exchange_rate::Column::Date.lte(date))
+        .order_by_desc(exchange_rate::Column::Date)
+        .one(db)
+        .await?;
+
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::test_database;
+
+    #[test]
+    fn it_parses_rates() {
+        let rates = parse_csv("date,from,to,rate\n\n# ECB\n2023-05-01, EUR, GBP, 0.87\n").unwrap();
+        assert_eq!(
+            rates,
+            vec![Rate {
+                date: NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+                from_currency: String::from("EUR"),
+                to_currency: String::from("GBP"),
+                rate: 0.87,
+            }]
+        );
+
+        let error = parse_csv("2023-05-01,EUR,XYZ,0.87").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1: unknown currency: `2023-05-01,EUR,XYZ,0.87`"
+        );
+        assert!(parse_csv("2023-05-01,EUR,GBP,-1").is_err());
+    }
+
+    #[tokio::test]
+    async fn it_finds_the_latest_rate_or_its_inverse() {
+        let db = test_database().await;
+        let rates = parse_csv("2023-05-01,EUR,GBP,0.8\n2023-05-03,EUR,GBP,0.5\n").unwrap();
+        import(&db, &rates).await.unwrap();
+        import(&db, &rates).await.unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2023, 5, d).unwrap();
+
+        assert_eq!(rate_on(&db, "EUR", "GBP", day(2)).await.unwrap(), Some(0.8));
+        assert_eq!(rate_on(&db, "EUR", "GBP", day(9)).await.unwrap(), Some(0.5));
+        assert_eq!(rate_on(&db, "GBP", "EUR", day(9)).await.unwrap(), Some(2.0));
+        assert_eq!(
+            rate_on(&db, "EUR", "GBP", day(1) - chrono::Duration::days(1))
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(rate_on(&db, "USD", "USD", day(1)).await.unwrap(), Some(1.0));
+        assert_eq!(ExchangeRate::find().all(&db).await.unwrap().len(), 2);
+    }
+}
@@ -1,5 +1,8 @@
 pub mod account;
 pub mod counterparty;
+pub mod exchange_rate;
+pub mod space;
+pub mod sync_state;
 pub mod transaction;
 use anyhow::Result;
 use sea_orm_migration::prelude::*;
@@ -20,7 +23,80 @@ pub async fn reset() -> Result<()> {
 
 pub async fn get_database() -> Result<DatabaseConnection> {
     let config = Config::new();
-    let con = Database::connect(&config.db_url()).await?;
+    connect(&config).await
+}
+
+/// Connect to the database described by the given configuration
+pub async fn connect(config: &Config) -> Result<DatabaseConnection> {
+    let con = Database::connect(&config.db_url()?).await?;
 
     Ok(con)
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{Backend, DbConfig};
+    use crate::starling::client::StarlingMockClient;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Create a fresh SQLite database in a temporary file, with all migrations applied
+    pub async fn test_database() -> DatabaseConnection {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "money-test-{}-{}.sqlite",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let config = Config {
+            db: DbConfig {
+                backend: Backend::Sqlite,
+                path: Some(path.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let db = connect(&config).await.unwrap();
+        Migrator::fresh(&db).await.unwrap();
+
+        db
+    }
+
+    /// Load the mock client for `fixtures/starling.json`
+    pub fn fixture_client() -> StarlingMockClient {
+        StarlingMockClient::from_fixture(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/starling.json"
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_migrates_a_sqlite_database() {
+        let db = test_database().await;
+        let manager = SchemaManager::new(&db);
+
+        for table in ["account", "counterparty", "sync_state", "transaction"] {
+            assert!(manager.has_table(table).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_enforces_foreign_keys_on_sqlite() {
+        let db = test_database().await;
+
+        // a transaction of an account and counterparty that aren't stored
+        let insert = Statement::from_string(
+            DbBackend::Sqlite,
+            String::from(
+                "INSERT INTO \"transaction\" (uid, account_uid, transaction_time, counterparty_id, \
+                 currency, spending_category, reference, user_note, status) \
+                 VALUES ('uid', 'unknown', '2023-05-01', 42, 'GBP', 'EATING_OUT', '', '', 'Settled')",
+            ),
+        );
+        let error = db.execute(insert).await.unwrap_err();
+        assert!(error.to_string().contains("FOREIGN KEY"), "{}", error);
+    }
+}
@@ -0,0 +1,101 @@
+//! Functions for interacting with table `space`
+
+use crate::entities::{prelude::*, space};
+use crate::starling::client::StarlingClient;
+use crate::starling::space::Space as StarlingSpace;
+use anyhow::Result;
+use sea_orm::*;
+
+/// Fetch an account's spaces from Starling, insert new ones and rename changed ones, and return
+/// the account's stored spaces
+pub async fn refresh(
+    db: &DatabaseConnection,
+    account_uid: &str,
+    client: &impl StarlingClient,
+) -> Result<Vec<space::Model>> {
+    for space in client.spaces(account_uid).await?.all() {
+        store_space(db, account_uid, &space).await?;
+    }
+
+    list_for_account(db, account_uid).await
+}
+
+/// List spaces, grouped by account
+pub async fn list(db: &DatabaseConnection) -> Result<Vec<space::Model>> {
+    let spaces = Space::find()
+        .order_by_asc(space::Column::AccountUid)
+        .order_by_asc(space::Column::Name)
+        .all(db)
+        .await?;
+
+    Ok(spaces)
+}
+
+/// List an account's spaces
+pub async fn list_for_account(
+    db: &DatabaseConnection,
+    account_uid: &str,
+) -> Result<Vec<space::Model>> {
+    let spaces = Space::find()
+        .filter(space::Column::AccountUid.eq(account_uid))
+        .order_by_asc(space::Column::Name)
+        .all(db)
+        .await?;
+
+    Ok(spaces)
+}
+
+// Insert a space, or update its name and kind if it's already stored
+async fn store_space(
+    db: &DatabaseConnection,
+    account_uid: &str,
+    space: &StarlingSpace,
+) -> Result<()> {
+    let existing = Space::find()
+        .filter(space::Column::Uid.eq(space.uid.as_str()))
+        .one(db)
+        .await?;
+
+    match existing {
+        None => {
+            let record = space::ActiveModel {
+                account_uid: ActiveValue::set(account_uid.to_string()),
+                uid: ActiveValue::set(space.uid.to_owned()),
+                name: ActiveValue::set(space.name.to_owned()),
+                kind: ActiveValue::set(space.kind.to_string()),
+                ..Default::default()
+            };
+            Space::insert(record).exec(db).await?;
+        }
+        Some(record) if record.name != space.name || record.kind != space.kind.to_string() => {
+            let mut record: space::ActiveModel = record.into();
+            record.name = ActiveValue::set(space.name.to_owned());
+            record.kind = ActiveValue::set(space.kind.to_string());
+            record.update(db).await?;
+        }
+        Some(_) => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::{fixture_client, test_database};
+
+    #[tokio::test]
+    async fn it_stores_each_space_once() {
+        let db = test_database().await;
+        let client = fixture_client();
+        let account_uid = &client.accounts[0].uid;
+
+        refresh(&db, account_uid, &client).await.unwrap();
+        let spaces = refresh(&db, account_uid, &client).await.unwrap();
+
+        assert_eq!(spaces.len(), 1);
+        assert_eq!(spaces[0].name, "Holiday");
+        assert_eq!(spaces[0].kind, "SavingsGoal");
+        assert_eq!(list(&db).await.unwrap(), spaces);
+    }
+}
@@ -0,0 +1,86 @@
+//! Functions for interacting with table `sync_state`
+//!
+//! Each row records when the feed for an account and category was last synced successfully.
+
+use crate::entities::{prelude::*, sync_state};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sea_orm::*;
+
+/// Return the time of the last successful sync of the account's category, if there has been one
+pub async fn last_synced(
+    db: &DatabaseConnection,
+    account_uid: &str,
+    category_uid: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    let state = find(db, account_uid, category_uid).await?;
+
+    Ok(state.map(|state| state.synced_at))
+}
+
+/// Record a successful sync of the account's category
+pub async fn set_synced(
+    db: &DatabaseConnection,
+    account_uid: &str,
+    category_uid: &str,
+    synced_at: DateTime<Utc>,
+) -> Result<()> {
+    match find(db, account_uid, category_uid).await? {
+        Some(state) => {
+            let mut state: sync_state::ActiveModel = state.into();
+            state.synced_at = ActiveValue::set(synced_at);
+            state.update(db).await?;
+        }
+        None => {
+            let state = sync_state::ActiveModel {
+                account_uid: ActiveValue::set(account_uid.to_string()),
+                category_uid: ActiveValue::set(category_uid.to_string()),
+                synced_at: ActiveValue::set(synced_at),
+                ..Default::default()
+            };
+            SyncState::insert(state).exec(db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn find(
+    db: &DatabaseConnection,
+    account_uid: &str,
+    category_uid: &str,
+) -> Result<Option<sync_state::Model>> {
+    let state = SyncState::find()
+        .filter(sync_state::Column::AccountUid.eq(account_uid))
+        .filter(sync_state::Column::CategoryUid.eq(category_uid))
+        .one(db)
+        .await?;
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::test_database;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn it_records_the_latest_sync() {
+        let db = test_database().await;
+        assert_eq!(last_synced(&db, "account", "category").await.unwrap(), None);
+
+        let first = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2023, 5, 2, 12, 0, 0).unwrap();
+        set_synced(&db, "account", "category", first).await.unwrap();
+        set_synced(&db, "account", "category", second)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            last_synced(&db, "account", "category").await.unwrap(),
+            Some(second)
+        );
+        assert_eq!(SyncState::find().all(&db).await.unwrap().len(), 1);
+    }
+}
@@ -1,93 +1,307 @@
 //! Services for interacting with the database.
 //!
 
-use super::get_database;
 use crate::db;
-use crate::entities::counterparty;
-use crate::starling::client::{StarlingApiClient, StarlingClient};
+use crate::entities::{account, counterparty};
+use crate::starling::client::StarlingClient;
 use crate::{
     entities::{prelude::*, transaction},
-    starling::transaction::StarlingTransaction,
+    starling::transaction::{Direction, StarlingTransaction, StarlingTransactions},
 };
 use anyhow::Result;
 
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::*;
+use std::collections::{HashMap, HashSet};
 
-/// Insert or update a list of Starling transactions for the specified account and number of days.
+/// How far before the last sync mark an incremental sync starts, to catch late-settling items
+pub const SYNC_OVERLAP_DAYS: i64 = 7;
+
+/// Insert or update a list of Starling transactions for every account and space for the specified
+/// number of days, using the client `client_for` returns for each account.
 ///
 /// If the transaction doesn't exist, insert it. If it exists and its status has changed, update it.
-pub async fn insert_or_update(days: i64) -> Result<()> {
-    let db = get_database().await.unwrap();
-    for account in db::account::list().await? {
-        // fetch the latest transactions
-
-        let client = StarlingApiClient::new(&account.token);
-        let transactions = client
-            .transactions_since(
-                &account.uid,
-                &account.default_category,
-                Duration::days(days),
-            )
-            .await;
-
-        for transaction in transactions {
-            match transaction_exists(&db, &transaction.uid).await {
-                None => {
-                    // insert or get the counterparty id
-
-                    let item_counterparty_uid =
-                        transaction.counterparty_uid.clone().unwrap_or_default();
-
-                    let counterparty_id = match counterparty_exists(&db, &item_counterparty_uid)
-                        .await
-                    {
-                        Some(counterparty) => counterparty.id,
-
-                        None => {
-                            let counterparty = counterparty_from_starling_feed_item(&transaction);
-                            let record = Counterparty::insert(counterparty)
-                                .exec(&db)
-                                .await
-                                .expect("inserting counterparty");
-                            record.last_insert_id
-                        }
-                    };
-
-                    // insert the new transaction
-
-                    let record =
-                        record_from_starling_feed_item(&transaction, counterparty_id, &account.uid);
-                    Transaction::insert(record)
-                        .exec(&db)
-                        .await
-                        .expect("inserting feed item");
-                }
+pub async fn insert_or_update<C: StarlingClient>(
+    db: &DatabaseConnection,
+    days: i64,
+    client_for: impl Fn(&account::Model) -> Result<C>,
+) -> Result<()> {
+    let mut earliest = None;
+    for account in db::account::list(db).await? {
+        let client = client_for(&account)?;
+        for category in categories(db, &account, &client).await? {
+            // fetch the latest transactions
+
+            let transactions = client
+                .transactions_since(
+                    &account.uid,
+                    &category.uid,
+                    Utc::now() - Duration::days(days),
+                )
+                .await?;
+            report_skipped(&transactions);
+
+            store_transactions(db, &account.uid, &category.uid, &transactions.feed_items).await?;
+            earliest = earliest_of(earliest, &transactions.feed_items);
+        }
+    }
+
+    link_new_transfers(db, earliest).await
+}
+
+/// Fetch and store the transactions changed since the last successful sync of each account's
+/// default category and of each of its spaces.
+///
+/// The fetch starts `SYNC_OVERLAP_DAYS` before the recorded mark so that late-settling items are
+/// picked up again. With `full`, or when there is no mark, the fetch starts at account creation.
+pub async fn sync<C: StarlingClient>(
+    db: &DatabaseConnection,
+    full: bool,
+    client_for: impl Fn(&account::Model) -> Result<C>,
+) -> Result<()> {
+    let mut earliest = None;
+    for account in db::account::list(db).await? {
+        let client = client_for(&account)?;
+        for category in categories(db, &account, &client).await? {
+            let mark = match full {
+                true => None,
+                false => db::sync_state::last_synced(db, &account.uid, &category.uid).await?,
+            };
+            let since = match mark {
+                Some(mark) => mark - Duration::days(SYNC_OVERLAP_DAYS),
+                None => account.created_at,
+            };
+
+            // record the time before fetching so nothing changed during the sync is missed next time
+            let started_at = Utc::now();
+            let transactions = client
+                .transactions_since(&account.uid, &category.uid, since)
+                .await?;
+
+            println!(
+                "{}: {} transaction(s) changed since {}",
+                category.name,
+                transactions.feed_items.len(),
+                since.format("%Y-%m-%d %H:%M")
+            );
+            report_skipped(&transactions);
+
+            store_transactions(db, &account.uid, &category.uid, &transactions.feed_items).await?;
+            db::sync_state::set_synced(db, &account.uid, &category.uid, started_at).await?;
+            earliest = earliest_of(earliest, &transactions.feed_items);
+        }
+    }
+
+    link_new_transfers(db, earliest).await
+}
+
+// A feed category of an account
+struct Category {
+    uid: String,
+    name: String,
+}
+
+// An account's default category followed by the categories of its spaces, which are refreshed
+// from Starling first
+async fn categories(
+    db: &DatabaseConnection,
+    account: &account::Model,
+    client: &impl StarlingClient,
+) -> Result<Vec<Category>> {
+    let default = Category {
+        uid: account.default_category.to_owned(),
+        name: account.name.to_owned(),
+    };
+    let spaces = db::space::refresh(db, &account.uid, client).await?;
+    let spaces = spaces.into_iter().map(|space| Category {
+        uid: space.uid,
+        name: format!("{} / {}", account.name, space.name),
+    });
+
+    Ok(std::iter::once(default).chain(spaces).collect())
+}
+
+/// Insert or update the settled Starling transactions for every account in the range `[from, to)`.
+pub async fn insert_or_update_between<C: StarlingClient>(
+    db: &DatabaseConnection,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    client_for: impl Fn(&account::Model) -> Result<C>,
+) -> Result<()> {
+    let mut earliest = None;
+    for account in db::account::list(db).await? {
+        let client = client_for(&account)?;
+        let transactions = client.transactions_between(&account.uid, from, to).await?;
+
+        println!(
+            "{}: {} settled transaction(s)",
+            account.name,
+            transactions.feed_items.len()
+        );
+        report_skipped(&transactions);
+
+        store_transactions(
+            db,
+            &account.uid,
+            &account.default_category,
+            &transactions.feed_items,
+        )
+        .await?;
+        earliest = earliest_of(earliest, &transactions.feed_items);
+    }
+
+    link_new_transfers(db, earliest).await
+}
+
+// Log the feed items that couldn't be decoded
+fn report_skipped(transactions: &StarlingTransactions) {
+    for skipped in transactions.skipped.iter() {
+        println!("Skipping {}", skipped);
+    }
+}
+
+// The earlier of `earliest` and the time of the earliest of `items`
+fn earliest_of(
+    earliest: Option<DateTime<Utc>>,
+    items: &[StarlingTransaction],
+) -> Option<DateTime<Utc>> {
+    earliest
+        .into_iter()
+        .chain(items.iter().map(|item| item.transaction_time))
+        .min()
+}
+
+// Link the transfers between own accounts among the newly stored transactions, the earliest of
+// which is at `earliest`
+async fn link_new_transfers(
+    db: &DatabaseConnection,
+    earliest: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let Some(earliest) = earliest else {
+        return Ok(());
+    };
+    let linked = link_transfers(db, Some(earliest)).await?;
+    if linked > 0 {
+        println!("{} transfer(s) between own accounts linked", linked);
+    }
+
+    Ok(())
+}
+
+/// Insert a Starling transaction for the given account, or update it if it has changed.
+///
+/// See `store_transactions`.
+pub async fn store_transaction(
+    db: &DatabaseConnection,
+    account_uid: &str,
+    category_uid: &str,
+    transaction: &StarlingTransaction,
+) -> Result<()> {
+    store_transactions(
+        db,
+        account_uid,
+        category_uid,
+        std::slice::from_ref(transaction),
+    )
+    .await
+}
 
-                Some(record) => {
-                    if transaction_changed(&record, &transaction) {
-                        // update the feed item status
-                        // TODO : refactor this to update status field only
-
-                        let new_status = transaction.status.to_string();
-                        let new_spending_category = transaction.spending_category;
-                        let new_user_note = transaction.user_note.clone().unwrap_or_default();
-
-                        let record = transaction::ActiveModel {
-                            account_uid: ActiveValue::Set(record.account_uid.to_owned()),
-                            status: ActiveValue::set(new_status.to_owned()),
-                            spending_category: ActiveValue::set(new_spending_category.to_owned()),
-                            user_note: ActiveValue::set(new_user_note.to_owned()),
-                            id: ActiveValue::Set(record.id.to_owned()),
-                            uid: ActiveValue::Set(record.uid.to_owned()),
-                            transaction_time: ActiveValue::set(record.transaction_time.to_owned()),
-                            counterparty_id: ActiveValue::set(record.counterparty_id.to_owned()),
-                            amount: ActiveValue::set(record.amount).to_owned(),
-                            currency: ActiveValue::set(record.currency.to_owned()),
-                            reference: ActiveValue::set(record.reference.to_owned()),
-                        };
-                        record.update(&db).await.expect("updating feed item");
+/// Insert Starling transactions for the given account, or update those that have changed.
+///
+/// Each transaction is recorded in its own `categoryUid` if it has one, else in `category_uid`,
+/// the category it was fetched from. Counterparties are inserted first if they aren't already
+/// known. Transactions with an unknown direction are logged and skipped.
+///
+/// The stored transactions and counterparties are looked up for the whole batch at once, rather
+/// than once per transaction.
+pub async fn store_transactions(
+    db: &DatabaseConnection,
+    account_uid: &str,
+    category_uid: &str,
+    transactions: &[StarlingTransaction],
+) -> Result<()> {
+    let mut stored: HashMap<String, transaction::Model> = find_in::<Transaction>(
+        db,
+        transaction::Column::Uid,
+        transactions.iter().map(|item| item.uid.to_owned()),
+    )
+    .await?
+    .into_iter()
+    .map(|record| (record.uid.to_owned(), record))
+    .collect();
+    let mut counterparty_ids: HashMap<String, i32> = find_in::<Counterparty>(
+        db,
+        counterparty::Column::Uid,
+        transactions.iter().map(counterparty_uid),
+    )
+    .await?
+    .into_iter()
+    .map(|counterparty| (counterparty.uid, counterparty.id))
+    .collect();
+    let own_accounts = db::account::OwnAccounts::load(db).await?;
+
+    for transaction in transactions {
+        // without a direction the amount can't be signed, so don't guess
+        if let Direction::Other(direction) = &transaction.direction {
+            println!(
+                "Skipping feed item {}: unknown direction `{}`",
+                transaction.uid, direction
+            );
+            continue;
+        }
+
+        match stored.get(&transaction.uid) {
+            None => {
+                // insert or get the counterparty id
+
+                let counterparty_id = match counterparty_ids.get(&counterparty_uid(transaction)) {
+                    Some(id) => *id,
+
+                    None => {
+                        let counterparty = counterparty_from_starling_feed_item(transaction);
+                        let id = Counterparty::insert(counterparty)
+                            .exec(db)
+                            .await?
+                            .last_insert_id;
+                        counterparty_ids.insert(counterparty_uid(transaction), id);
+                        id
                     }
+                };
+
+                // insert the new transaction
+
+                let mut record = record_from_starling_feed_item(
+                    transaction,
+                    counterparty_id,
+                    account_uid,
+                    category_uid,
+                );
+                record.counterparty_account_uid =
+                    ActiveValue::set(own_accounts.account_for(transaction));
+                let record = Transaction::insert(record).exec_with_returning(db).await?;
+                stored.insert(record.uid.to_owned(), record);
+            }
+
+            Some(record) => {
+                if transaction_changed(record, transaction) {
+                    // update only the fields that can change
+
+                    let mut record: transaction::ActiveModel = record.clone().into();
+                    record.status = ActiveValue::set(transaction.status.to_string());
+                    record.spending_category =
+                        ActiveValue::set(transaction.spending_category.to_owned());
+                    record.user_note =
+                        ActiveValue::set(transaction.user_note.clone().unwrap_or_default());
+                    record.settlement_time = ActiveValue::set(transaction.settlement_time);
+                    record.updated_at = ActiveValue::set(transaction.updated_at);
+                    record.round_up_amount = ActiveValue::set(round_up_amount(transaction));
+                    record.round_up_goal_uid = ActiveValue::set(round_up_goal_uid(transaction));
+                    record.has_attachment = ActiveValue::set(transaction.has_attachment);
+                    record.has_receipt = ActiveValue::set(transaction.has_receipt);
+                    record.raw = ActiveValue::set(raw_payload(transaction));
+                    let record = record.update(db).await?;
+                    stored.insert(record.uid.to_owned(), record);
                 }
             }
         }
@@ -96,63 +310,816 @@ pub async fn insert_or_update(days: i64) -> Result<()> {
     Ok(())
 }
 
-/// Return true if a feed item with the given feed uid exists in the database.
-async fn transaction_exists(
+/// Recognise the counterparties of stored transactions that are stored accounts, e.g. after their
+/// details have been fetched, returning the number of transactions matched
+pub async fn match_own_accounts(db: &DatabaseConnection) -> Result<u64> {
+    let own_accounts = db::account::OwnAccounts::load(db).await?;
+    let unmatched = Transaction::find()
+        .filter(transaction::Column::CounterpartyAccountUid.is_null())
+        .filter(transaction::Column::Raw.is_not_null())
+        .all(db)
+        .await?;
+
+    let mut matched = 0;
+    for record in unmatched {
+        let item: Option<StarlingTransaction> = record
+            .raw
+            .as_deref()
+            .and_then(|raw| serde_json::from_str(raw).ok());
+        let Some(account_uid) = item.and_then(|item| own_accounts.account_for(&item)) else {
+            continue;
+        };
+        let mut record: transaction::ActiveModel = record.into();
+        record.counterparty_account_uid = ActiveValue::set(Some(account_uid));
+        record.update(db).await?;
+        matched += 1;
+    }
+
+    Ok(matched)
+}
+
+/// How far apart in time the two legs of a transfer between own accounts can be
+pub const TRANSFER_WINDOW_DAYS: i64 = 3;
+
+// The statuses of transactions that moved money, so can be a leg of a transfer
+const TRANSFER_STATUSES: [&str; 2] = ["Settled", "Pending"];
+
+/// Link the outgoing and incoming legs of transfers between own accounts, returning the number of
+/// transfers linked.
+///
+/// A transaction whose counterparty is a stored account (see `match_own_accounts`) is linked to an
+/// unlinked transaction in that account for the opposite amount in the same currency, no more than
+/// `TRANSFER_WINDOW_DAYS` apart, whose own counterparty isn't a different account; the closest in
+/// time is chosen. Links to legs that have since been declined or reversed are removed first.
+///
+/// With `from`, only legs that could be a transfer with a transaction at or after `from` are
+/// considered, e.g. those a sync stored, rather than every unlinked leg in the history.
+pub async fn link_transfers(db: &DatabaseConnection, from: Option<DateTime<Utc>>) -> Result<u64> {
+    let txn = db.begin().await?;
+
+    // unlink transfers where either leg no longer moved money
+    let stale: Vec<String> = Transaction::find()
+        .filter(transaction::Column::TransferUid.is_not_null())
+        .filter(transaction::Column::Status.is_not_in(TRANSFER_STATUSES))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .flat_map(|record| [Some(record.uid), record.transfer_uid])
+        .flatten()
+        .collect();
+    if !stale.is_empty() {
+        set_transfer_uid(&txn, stale, None).await?;
+    }
+
+    let window = Duration::days(TRANSFER_WINDOW_DAYS);
+    let mut legs = Transaction::find()
+        .filter(transaction::Column::TransferUid.is_null())
+        .filter(transaction::Column::CounterpartyAccountUid.is_not_null())
+        .filter(transaction::Column::Status.is_in(TRANSFER_STATUSES));
+    if let Some(from) = from {
+        legs = legs.filter(transaction::Column::TransactionTime.gte(from - window));
+    }
+    let legs = legs
+        .order_by_asc(transaction::Column::TransactionTime)
+        .all(&txn)
+        .await?;
+    let (Some(first), Some(last)) = (legs.first(), legs.last()) else {
+        txn.commit().await?;
+        return Ok(0);
+    };
+
+    // the unlinked transactions in the accounts paid or paid from, around the same times
+    let accounts: HashSet<&String> = legs
+        .iter()
+        .filter_map(|leg| leg.counterparty_account_uid.as_ref())
+        .collect();
+    let candidates = Transaction::find()
+        .filter(transaction::Column::TransferUid.is_null())
+        .filter(transaction::Column::Status.is_in(TRANSFER_STATUSES))
+        .filter(transaction::Column::AccountUid.is_in(accounts))
+        .filter(transaction::Column::TransactionTime.between(
+            first.transaction_time - window,
+            last.transaction_time + window,
+        ))
+        .all(&txn)
+        .await?;
+
+    let mut linked = HashSet::new();
+    for leg in legs.iter() {
+        if linked.contains(&leg.uid) {
+            continue;
+        }
+        let other = candidates
+            .iter()
+            .filter(|other| !linked.contains(&other.uid) && is_other_leg(leg, other, window))
+            .min_by_key(|other| time_apart(leg, other));
+        let Some(other) = other else {
+            continue;
+        };
+
+        set_transfer_uid(&txn, vec![leg.uid.to_owned()], Some(&other.uid)).await?;
+        set_transfer_uid(&txn, vec![other.uid.to_owned()], Some(&leg.uid)).await?;
+        linked.insert(leg.uid.to_owned());
+        linked.insert(other.uid.to_owned());
+    }
+    txn.commit().await?;
+
+    Ok(linked.len() as u64 / 2)
+}
+
+// Return true if `other` can be the other leg of the transfer `leg` made with a stored account
+fn is_other_leg(leg: &transaction::Model, other: &transaction::Model, window: Duration) -> bool {
+    leg.counterparty_account_uid.as_ref() == Some(&other.account_uid)
+        && other.account_uid != leg.account_uid
+        && other
+            .counterparty_account_uid
+            .as_ref()
+            .is_none_or(|uid| *uid == leg.account_uid)
+        && leg.amount != 0
+        && other.amount == -leg.amount
+        && other.currency == leg.currency
+        && time_apart(leg, other) <= window
+}
+
+fn time_apart(leg: &transaction::Model, other: &transaction::Model) -> Duration {
+    let apart = other.transaction_time - leg.transaction_time;
+    if apart < Duration::zero() {
+        -apart
+    } else {
+        apart
+    }
+}
+
+async fn set_transfer_uid(
+    db: &impl ConnectionTrait,
+    uids: Vec<String>,
+    transfer_uid: Option<&String>,
+) -> Result<()> {
+    Transaction::update_many()
+        .col_expr(
+            transaction::Column::TransferUid,
+            Expr::value(transfer_uid.cloned()),
+        )
+        .filter(transaction::Column::Uid.is_in(uids))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// List transactions, oldest first, optionally restricted to the time range `[from, to)` and to
+/// the given feed categories.
+pub async fn list_between(
     db: &DatabaseConnection,
-    transaction_uid: &String,
-) -> Option<transaction::Model> {
-    Transaction::find()
-        .filter(transaction::Column::Uid.eq(transaction_uid))
-        .one(db)
-        .await
-        .expect("getting feed id")
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    categories: Option<&[String]>,
+) -> Result<Vec<transaction::Model>> {
+    let transactions = select_between(from, to, categories).all(db).await?;
+
+    Ok(transactions)
+}
+
+/// The number of stored transactions of the given accounts
+pub async fn count_for_accounts(db: &impl ConnectionTrait, account_uids: &[String]) -> Result<u64> {
+    let count = Transaction::find()
+        .filter(transaction::Column::AccountUid.is_in(account_uids.iter().cloned()))
+        .count(db)
+        .await?;
+
+    Ok(count)
+}
+
+/// A stored transaction with its account and counterparty
+#[derive(Debug, Clone)]
+pub struct TransactionDetail {
+    pub transaction: transaction::Model,
+    pub account: Option<account::Model>,
+    pub counterparty: Option<counterparty::Model>,
+}
+
+// Column alias prefixes for the joined account and counterparty
+const ACCOUNT_PREFIX: &str = "a_";
+const COUNTERPARTY_PREFIX: &str = "c_";
+
+impl FromQueryResult for TransactionDetail {
+    fn from_query_result(res: &QueryResult, pre: &str) -> Result<Self, DbErr> {
+        Ok(Self {
+            transaction: transaction::Model::from_query_result(res, pre)?,
+            account: account::Model::from_query_result_optional(res, ACCOUNT_PREFIX)?,
+            counterparty: counterparty::Model::from_query_result_optional(
+                res,
+                COUNTERPARTY_PREFIX,
+            )?,
+        })
+    }
+}
+
+/// Like `list_between`, but with each transaction's account and counterparty, fetched in the same
+/// query.
+pub async fn list_with_details(
+    db: &DatabaseConnection,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    categories: Option<&[String]>,
+) -> Result<Vec<TransactionDetail>> {
+    let mut query = select_between(from, to, categories)
+        .join(JoinType::LeftJoin, transaction::Relation::Account.def())
+        .join(
+            JoinType::LeftJoin,
+            transaction::Relation::Counterparty.def(),
+        );
+    for column in account::Column::iter() {
+        query = query.column_as(
+            Expr::col((Account, column)),
+            format!("{}{}", ACCOUNT_PREFIX, column.as_str()),
+        );
+    }
+    for column in counterparty::Column::iter() {
+        query = query.column_as(
+            Expr::col((Counterparty, column)),
+            format!("{}{}", COUNTERPARTY_PREFIX, column.as_str()),
+        );
+    }
+    let details = query.into_model::<TransactionDetail>().all(db).await?;
+
+    Ok(details)
+}
+
+// Select transactions, oldest first, in the time range and categories given
+fn select_between(
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    categories: Option<&[String]>,
+) -> Select<Transaction> {
+    let mut query = Transaction::find();
+    if let Some(categories) = categories {
+        query = query.filter(transaction::Column::CategoryUid.is_in(categories.iter().cloned()));
+    }
+    if let Some(from) = from {
+        query = query.filter(transaction::Column::TransactionTime.gte(from));
+    }
+    if let Some(to) = to {
+        query = query.filter(transaction::Column::TransactionTime.lt(to));
+    }
+
+    query.order_by_asc(transaction::Column::TransactionTime)
+}
+
+/// The most values put in one `IN (...)` list, well within every backend's limit on parameters
+const LOOKUP_CHUNK_SIZE: usize = 500;
+
+// Find the rows of an entity whose `column` is one of `values`
+async fn find_in<E: EntityTrait>(
+    db: &DatabaseConnection,
+    column: E::Column,
+    values: impl Iterator<Item = String>,
+) -> Result<Vec<E::Model>> {
+    let values: Vec<String> = values.collect();
+    let mut models = Vec::new();
+    for chunk in values.chunks(LOOKUP_CHUNK_SIZE) {
+        let found = E::find()
+            .filter(column.is_in(chunk.iter().cloned()))
+            .all(db)
+            .await?;
+        models.extend(found);
+    }
+
+    Ok(models)
 }
 
-// Return true if status or spending category has changed
+// Return true if the feed item was updated, or its status, spending category or note has changed
 fn transaction_changed(record: &transaction::Model, newitem: &StarlingTransaction) -> bool {
     (record.status != newitem.status.to_string())
         || (record.spending_category != newitem.spending_category)
-        || (record.user_note != newitem.user_note.clone().unwrap_or_default().to_string())
+        || (record.user_note != newitem.user_note.clone().unwrap_or_default())
+        || (record.updated_at != newitem.updated_at)
 }
 
-// Return true if a counterparty with the given counterparty uid exists in the database.
-async fn counterparty_exists(
-    db: &DatabaseConnection,
-    counterparty_uid: &String,
-) -> Option<counterparty::Model> {
-    Counterparty::find()
-        .filter(counterparty::Column::Uid.eq(counterparty_uid))
-        .one(db)
-        .await
-        .expect("getting counterparty id")
+// The counterparty UID of a feed item, empty if it has none
+fn counterparty_uid(item: &StarlingTransaction) -> String {
+    item.counterparty_uid.clone().unwrap_or_default()
 }
 
 fn record_from_starling_feed_item(
     item: &StarlingTransaction,
     counterparty_id: i32,
     account_uid: &str,
+    category_uid: &str,
 ) -> transaction::ActiveModel {
+    let category_uid = item.category_uid.as_deref().unwrap_or(category_uid);
     transaction::ActiveModel {
         uid: ActiveValue::Set(item.uid.to_owned()),
         account_uid: ActiveValue::Set(account_uid.to_string()),
+        category_uid: ActiveValue::Set(category_uid.to_string()),
         transaction_time: ActiveValue::Set(item.transaction_time.to_owned()),
         counterparty_id: ActiveValue::Set(counterparty_id),
-        amount: ActiveValue::set(item.amount()),
+        amount: ActiveValue::set(item.amount().minor_units),
         spending_category: ActiveValue::set(item.spending_category.to_owned()),
         currency: ActiveValue::set(item.currency()),
+        source_amount: ActiveValue::set(item.source_amount().map(|amount| amount.minor_units)),
+        source_currency: ActiveValue::set(item.source_amount().map(|amount| amount.currency)),
         reference: ActiveValue::set(item.reference.clone().unwrap_or_default()),
         user_note: ActiveValue::set(item.user_note.clone().unwrap_or_default()),
         status: ActiveValue::set(item.status.to_string()),
+        mcc: ActiveValue::set(item.mcc),
+        transacting_user_uid: ActiveValue::set(item.transacting_user_uid.clone()),
+        settlement_time: ActiveValue::set(item.settlement_time),
+        updated_at: ActiveValue::set(item.updated_at),
+        source: ActiveValue::set(item.source.clone()),
+        country: ActiveValue::set(item.country.clone()),
+        round_up_amount: ActiveValue::set(round_up_amount(item)),
+        round_up_goal_uid: ActiveValue::set(round_up_goal_uid(item)),
+        has_attachment: ActiveValue::set(item.has_attachment),
+        has_receipt: ActiveValue::set(item.has_receipt),
+        batch_payment_uid: ActiveValue::set(
+            item.batch_payment_details
+                .as_ref()
+                .map(|batch| batch.batch_payment_uid.to_owned()),
+        ),
+        batch_payment_type: ActiveValue::set(
+            item.batch_payment_details
+                .as_ref()
+                .map(|batch| batch.batch_payment_type.to_owned()),
+        ),
+        raw: ActiveValue::set(raw_payload(item)),
         ..Default::default()
     }
 }
 
+fn round_up_amount(item: &StarlingTransaction) -> Option<i64> {
+    item.round_up
+        .as_ref()
+        .map(|round_up| round_up.amount.minor_units)
+}
+
+fn round_up_goal_uid(item: &StarlingTransaction) -> Option<String> {
+    item.round_up
+        .as_ref()
+        .map(|round_up| round_up.goal_category_uid.to_owned())
+}
+
+// The feed item as the API sent it, or as JSON including any fields that aren't modelled if it
+// wasn't decoded from a page of feed items
+fn raw_payload(item: &StarlingTransaction) -> Option<String> {
+    match &item.raw {
+        Some(raw) => serde_json::to_string(raw).ok(),
+        None => serde_json::to_string(item).ok(),
+    }
+}
+
 fn counterparty_from_starling_feed_item(item: &StarlingTransaction) -> counterparty::ActiveModel {
-    let item_counterparty_uid = item.counterparty_uid.clone().unwrap_or_default();
     counterparty::ActiveModel {
-        uid: ActiveValue::Set(item_counterparty_uid),
+        uid: ActiveValue::Set(counterparty_uid(item)),
         name: ActiveValue::Set(item.counterparty_name.to_owned()),
         r#type: ActiveValue::Set(item.counterparty_type.to_owned()),
         ..Default::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::{fixture_client, test_database};
+    use crate::starling::account::{Account as StarlingAccount, AccountIdentifiers};
+    use crate::starling::client::StarlingMockClient;
+    use migration::{Migrator, MigratorTrait};
+
+    fn feed_item(status: &str, user_note: &str) -> StarlingTransaction {
+        serde_json::from_str(&format!(
+            r#"{{
+                "feedItemUid": "feed-uid",
+                "amount": {{ "currency": "GBP", "minorUnits": 1250 }},
+                "counterPartyName": "Pizza Place",
+                "counterPartyType": "MERCHANT",
+                "counterPartyUid": "counterparty-uid",
+                "direction": "OUT",
+                "reference": "CARD 1234",
+                "spendingCategory": "EATING_OUT",
+                "status": "{}",
+                "transactionTime": "2023-05-01T12:00:00.000Z",
+                "userNote": "{}"
+            }}"#,
+            status, user_note
+        ))
+        .unwrap()
+    }
+
+    // Store the account `account-uid` that transactions belong to
+    async fn insert_test_account(db: &DatabaseConnection) {
+        let account = serde_json::from_str(
+            r#"{
+                "accountUid": "account-uid",
+                "createdAt": "2019-06-01T09:00:00.000Z",
+                "defaultCategory": "category-uid",
+                "name": "Personal"
+            }"#,
+        )
+        .unwrap();
+        db::account::insert_account(&account, &String::from("token"), db)
+            .await
+            .unwrap();
+    }
+
+    // Store a transaction twice, changing its status, and check a single updated row results
+    async fn inserts_then_updates(db: &DatabaseConnection) {
+        insert_test_account(db).await;
+        store_transaction(db, "account-uid", "category-uid", &feed_item("PENDING", ""))
+            .await
+            .unwrap();
+        store_transaction(
+            db,
+            "account-uid",
+            "category-uid",
+            &feed_item("SETTLED", "pizza night"),
+        )
+        .await
+        .unwrap();
+
+        let records = Transaction::find().all(db).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, "Settled");
+        assert_eq!(records[0].user_note, "pizza night");
+        assert_eq!(records[0].amount, -1250);
+        assert_eq!(
+            records[0].transaction_time.to_rfc3339(),
+            "2023-05-01T12:00:00+00:00"
+        );
+        assert_eq!(Counterparty::find().all(db).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_syncs_from_a_client() {
+        let db = test_database().await;
+        let client = fixture_client();
+        db::account::add(&db, &client, &String::from("token"))
+            .await
+            .unwrap();
+
+        sync(&db, false, |_| Ok(client.clone())).await.unwrap();
+        sync(&db, false, |_| Ok(client.clone())).await.unwrap();
+
+        let records = list_between(&db, None, None, None).await.unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].amount, 250000);
+        assert_eq!(records[1].amount, -1250);
+
+        // the space's own feed holds the other side of the transfer into it
+        let space = &client.spaces[&client.accounts[0].uid].savings_goals[0];
+        let in_space = list_between(&db, None, None, Some(&[space.uid.to_owned()]))
+            .await
+            .unwrap();
+        assert_eq!(in_space.len(), 1);
+        assert_eq!(in_space[0].amount, 10000);
+
+        let account = &client.accounts[0];
+        for category in [
+            &account.default_category,
+            &client.spaces[&account.uid].savings_goals[0].uid,
+        ] {
+            let mark = db::sync_state::last_synced(&db, &account.uid, category)
+                .await
+                .unwrap();
+            assert!(mark.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_lists_transactions_with_their_account_and_counterparty() {
+        let db = test_database().await;
+        let client = fixture_client();
+        db::account::add(&db, &client, &String::from("token"))
+            .await
+            .unwrap();
+        sync(&db, false, |_| Ok(client.clone())).await.unwrap();
+
+        let details = list_with_details(&db, None, None, None).await.unwrap();
+        assert_eq!(details.len(), 4);
+        let salary = &details[0];
+        assert_eq!(salary.transaction.amount, 250000);
+        assert_eq!(salary.account.as_ref().unwrap().name, "Personal");
+        assert_eq!(
+            salary.counterparty.as_ref().unwrap().id,
+            salary.transaction.counterparty_id
+        );
+        assert_eq!(
+            details[1].counterparty.as_ref().unwrap().name,
+            "Pizza Place"
+        );
+
+        let account = Account::find().one(&db).await.unwrap().unwrap();
+        let transactions = account.find_related(Transaction).all(&db).await.unwrap();
+        assert_eq!(transactions.len(), 4);
+    }
+
+    // A transfer of £50 from the fixture's Personal account to the Joint account of
+    // `with_joint_account`: as Personal sees it if `direction` is OUT, as Joint sees it if IN
+    fn transfer_item(uid: &str, direction: &str, status: &str, time: &str) -> StarlingTransaction {
+        let (name, sort_code, account_number) = match direction {
+            "OUT" => ("Joint", "60-83-71", "87654321"),
+            _ => ("Personal", "608371", "12345678"),
+        };
+        serde_json::from_str(&format!(
+            r#"{{
+                "feedItemUid": "{}",
+                "amount": {{ "currency": "GBP", "minorUnits": 5000 }},
+                "counterPartyName": "{}",
+                "counterPartySubEntityIdentifier": "{}",
+                "counterPartySubEntitySubIdentifier": "{}",
+                "counterPartyType": "{}",
+                "direction": "{}",
+                "spendingCategory": "TRANSFERS",
+                "status": "{}",
+                "transactionTime": "{}"
+            }}"#,
+            uid,
+            name,
+            sort_code,
+            account_number,
+            if direction == "OUT" {
+                "PAYEE"
+            } else {
+                "SENDER"
+            },
+            direction,
+            status,
+            time
+        ))
+        .unwrap()
+    }
+
+    // Add a Joint account, with its bank identifiers, to a client
+    fn with_joint_account(client: StarlingMockClient) -> StarlingMockClient {
+        let joint: StarlingAccount = serde_json::from_str(
+            r#"{
+                "accountUid": "joint-uid",
+                "createdAt": "2020-01-01T09:00:00.000Z",
+                "defaultCategory": "joint-category-uid",
+                "name": "Joint"
+            }"#,
+        )
+        .unwrap();
+        let identifiers = AccountIdentifiers {
+            account_identifier: String::from("87654321"),
+            bank_identifier: String::from("608371"),
+            iban: None,
+            bic: None,
+        };
+        client
+            .with_account(joint)
+            .with_identifiers("joint-uid", identifiers)
+    }
+
+    async fn find_stored(db: &DatabaseConnection, uid: &str) -> transaction::Model {
+        Transaction::find()
+            .filter(transaction::Column::Uid.eq(uid))
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_recognises_own_accounts_as_counterparties() {
+        let db = test_database().await;
+        let client = fixture_client();
+        db::account::add(&db, &client, &String::from("token"))
+            .await
+            .unwrap();
+        let personal = client.accounts[0].uid.clone();
+        let transfer = |uid| transfer_item(uid, "OUT", "SETTLED", "2023-05-05T12:00:00.000Z");
+
+        // before the joint account is added its transfers can't be recognised
+        store_transaction(&db, &personal, "category", &transfer("transfer-1"))
+            .await
+            .unwrap();
+        let stored = find_stored(&db, "transfer-1").await;
+        assert_eq!(stored.counterparty_account_uid, None);
+
+        let client = with_joint_account(client);
+        db::account::add(&db, &client, &String::from("token"))
+            .await
+            .unwrap();
+
+        assert_eq!(match_own_accounts(&db).await.unwrap(), 1);
+        let joint_uid = Some(String::from("joint-uid"));
+        let stored = find_stored(&db, "transfer-1").await;
+        assert_eq!(stored.counterparty_account_uid, joint_uid);
+
+        store_transaction(&db, &personal, "category", &transfer("transfer-2"))
+            .await
+            .unwrap();
+        let stored = find_stored(&db, "transfer-2").await;
+        assert_eq!(stored.counterparty_account_uid, joint_uid);
+    }
+
+    #[tokio::test]
+    async fn it_links_transfers_between_own_accounts() {
+        let db = test_database().await;
+        let client = with_joint_account(fixture_client());
+        db::account::add(&db, &client, &String::from("token"))
+            .await
+            .unwrap();
+        let personal = client.accounts[0].uid.clone();
+
+        let sent = transfer_item("sent", "OUT", "SETTLED", "2023-05-05T12:00:00.000Z");
+        let received = transfer_item("received", "IN", "SETTLED", "2023-05-05T12:00:02.000Z");
+        // the same amount from Personal again, but too late to be the same transfer
+        let later = transfer_item("later", "IN", "SETTLED", "2023-05-12T12:00:00.000Z");
+        store_transaction(&db, &personal, "category", &sent)
+            .await
+            .unwrap();
+        for item in [&received, &later] {
+            store_transaction(&db, "joint-uid", "joint-category-uid", item)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(link_transfers(&db, None).await.unwrap(), 1);
+        assert_eq!(link_transfers(&db, None).await.unwrap(), 0);
+        let linked = |uid: &str| Some(uid.to_string());
+        assert_eq!(
+            find_stored(&db, "sent").await.transfer_uid,
+            linked("received")
+        );
+        assert_eq!(
+            find_stored(&db, "received").await.transfer_uid,
+            linked("sent")
+        );
+        assert_eq!(find_stored(&db, "later").await.transfer_uid, None);
+
+        // a leg that is reversed is unlinked, and the other leg can be linked again
+        let reversed = transfer_item("received", "IN", "REVERSED", "2023-05-05T12:00:02.000Z");
+        store_transaction(&db, "joint-uid", "joint-category-uid", &reversed)
+            .await
+            .unwrap();
+        assert_eq!(link_transfers(&db, None).await.unwrap(), 0);
+        assert_eq!(find_stored(&db, "sent").await.transfer_uid, None);
+        assert_eq!(find_stored(&db, "received").await.transfer_uid, None);
+    }
+
+    #[tokio::test]
+    async fn it_links_only_transfers_near_the_stored_transactions() {
+        let db = test_database().await;
+        let client = with_joint_account(fixture_client());
+        db::account::add(&db, &client, &String::from("token"))
+            .await
+            .unwrap();
+        let personal = client.accounts[0].uid.clone();
+
+        let sent = transfer_item("sent", "OUT", "SETTLED", "2023-01-05T12:00:00.000Z");
+        let received = transfer_item("received", "IN", "SETTLED", "2023-01-07T12:00:00.000Z");
+        store_transaction(&db, &personal, "category", &sent)
+            .await
+            .unwrap();
+        store_transaction(&db, "joint-uid", "joint-category-uid", &received)
+            .await
+            .unwrap();
+
+        // a sync that stored nothing before May doesn't look back at January
+        let may = "2023-05-01T00:00:00Z".parse().unwrap();
+        assert_eq!(link_transfers(&db, Some(may)).await.unwrap(), 0);
+        assert_eq!(find_stored(&db, "sent").await.transfer_uid, None);
+
+        // the later leg is enough to find the earlier one, up to the window before it
+        let received_at = received.transaction_time;
+        assert_eq!(link_transfers(&db, Some(received_at)).await.unwrap(), 1);
+        assert_eq!(
+            find_stored(&db, "sent").await.transfer_uid.as_deref(),
+            Some("received")
+        );
+    }
+
+    #[tokio::test]
+    async fn it_backfills_settled_transactions_only() {
+        let db = test_database().await;
+        let client = fixture_client();
+        db::account::add(&db, &client, &String::from("token"))
+            .await
+            .unwrap();
+
+        let from = Utc::now() - Duration::days(5 * 365);
+        insert_or_update_between(&db, from, Utc::now(), |_| Ok(client.clone()))
+            .await
+            .unwrap();
+
+        let records = list_between(&db, None, None, None).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|record| record.status == "Settled"));
+    }
+
+    #[tokio::test]
+    async fn it_stores_the_source_amount_of_foreign_purchases() {
+        let db = test_database().await;
+        insert_test_account(&db).await;
+        let mut item = feed_item("SETTLED", "");
+        item.source_amount =
+            serde_json::from_str(r#"{ "currency": "EUR", "minorUnits": 1440 }"#).unwrap();
+        store_transaction(&db, "account-uid", "category-uid", &item)
+            .await
+            .unwrap();
+
+        let records = Transaction::find().all(&db).await.unwrap();
+        assert_eq!(records[0].amount, -1250);
+        assert_eq!(records[0].source_amount, Some(-1440));
+        assert_eq!(records[0].source_currency.as_deref(), Some("EUR"));
+    }
+
+    #[tokio::test]
+    async fn it_stores_the_full_feed_item() {
+        let db = test_database().await;
+        insert_test_account(&db).await;
+        let page: StarlingTransactions = serde_json::from_str(
+            r#"{"feedItems": [{
+                "feedItemUid": "feed-uid",
+                "amount": { "currency": "GBP", "minorUnits": 1250 },
+                "counterPartyName": "Pizza Place",
+                "counterPartyType": "MERCHANT",
+                "direction": "OUT",
+                "spendingCategory": "EATING_OUT",
+                "status": "SETTLED",
+                "transactionTime": "2023-05-01T12:00:00.000Z",
+                "settlementTime": "2023-05-02T08:00:00.000Z",
+                "updatedAt": "2023-05-02T08:00:00.000Z",
+                "source": "MASTER_CARD",
+                "country": "GB",
+                "mcc": "5812",
+                "transactingApplicationUserUid": "user-uid",
+                "roundUp": {
+                    "goalCategoryUid": "goal-uid",
+                    "amount": { "currency": "GBP", "minorUnits": 50 }
+                },
+                "hasAttachment": false,
+                "hasReceipt": true,
+                "batchPaymentDetails": {
+                    "batchPaymentUid": "batch-uid",
+                    "batchPaymentType": "BULK_PAYMENT"
+                },
+                "retryAllocationUntilTime": "2023-05-09T12:00:00.000Z"
+            }]}"#,
+        )
+        .unwrap();
+        let item = &page.feed_items[0];
+        store_transaction(&db, "account-uid", "category-uid", item)
+            .await
+            .unwrap();
+
+        let record = &Transaction::find().all(&db).await.unwrap()[0];
+        assert_eq!(record.mcc, Some(5812));
+        assert_eq!(record.source.as_deref(), Some("MASTER_CARD"));
+        assert_eq!(record.country.as_deref(), Some("GB"));
+        assert_eq!(record.transacting_user_uid.as_deref(), Some("user-uid"));
+        assert_eq!(record.settlement_time, item.settlement_time);
+        assert_eq!(record.round_up_amount, Some(50));
+        assert_eq!(record.round_up_goal_uid.as_deref(), Some("goal-uid"));
+        assert!(record.has_receipt && !record.has_attachment);
+        assert_eq!(record.batch_payment_type.as_deref(), Some("BULK_PAYMENT"));
+
+        // the payload is kept as it was sent, with fields that aren't modelled
+        let raw: serde_json::Value = serde_json::from_str(record.raw.as_ref().unwrap()).unwrap();
+        assert_eq!(raw["retryAllocationUntilTime"], "2023-05-09T12:00:00.000Z");
+        assert_eq!(raw["feedItemUid"], "feed-uid");
+        assert_eq!(raw["mcc"], "5812");
+        assert!(raw.get("userNote").is_none());
+    }
+
+    #[tokio::test]
+    async fn it_stores_unknown_statuses_and_skips_unknown_directions() {
+        let db = test_database().await;
+        insert_test_account(&db).await;
+        let mut item = feed_item("ACCOUNT_CHECK", "");
+        store_transaction(&db, "account-uid", "category-uid", &item)
+            .await
+            .unwrap();
+        item.uid = String::from("other-uid");
+        item.direction = Direction::Other(String::from("SIDEWAYS"));
+        store_transaction(&db, "account-uid", "category-uid", &item)
+            .await
+            .unwrap();
+
+        let records = Transaction::find().all(&db).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, "ACCOUNT_CHECK");
+    }
+
+    #[tokio::test]
+    async fn it_stores_transactions_in_sqlite() {
+        let db = test_database().await;
+        inserts_then_updates(&db).await;
+    }
+
+    /// Runs against a throwaway Postgres server, e.g.
+    ///
+    /// ```text
+    /// docker run --rm -e POSTGRES_PASSWORD=pw -p 5432:5432 postgres
+    /// MONEY_TEST_POSTGRES_URL=postgres://postgres:pw@localhost/postgres cargo test -- --ignored
+    /// ```
+    #[tokio::test]
+    #[ignore = "needs a Postgres server in MONEY_TEST_POSTGRES_URL"]
+    async fn it_stores_transactions_in_postgres() {
+        let url = std::env::var("MONEY_TEST_POSTGRES_URL").expect("MONEY_TEST_POSTGRES_URL");
+        let db = Database::connect(&url).await.unwrap();
+        Migrator::fresh(&db).await.unwrap();
+        inserts_then_updates(&db).await;
+    }
+}
@@ -8,14 +8,31 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub name: String,
-    #[sea_orm(column_type = "Binary(BlobSize::Blob(Some(16)))")]
     pub uid: String,
     pub created_at: DateTimeUtc,
     pub default_category: String,
+    /// The access token, encrypted; see `crate::crypto`
+    #[sea_orm(column_type = "Text")]
     pub token: String,
+    pub account_holder_uid: Option<String>,
+    pub account_holder_type: Option<String>,
+    pub account_holder_name: Option<String>,
+    pub sort_code: Option<String>,
+    pub account_number: Option<String>,
+    pub iban: Option<String>,
+    pub bic: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::transaction::Entity")]
+    Transaction,
+}
+
+impl Related<super::transaction::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Transaction.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
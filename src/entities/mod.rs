@@ -4,4 +4,7 @@ pub mod prelude;
 
 pub mod account;
 pub mod counterparty;
+pub mod exchange_rate;
+pub mod space;
+pub mod sync_state;
 pub mod transaction;
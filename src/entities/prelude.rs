@@ -2,4 +2,7 @@
 
 pub use super::account::Entity as Account;
 pub use super::counterparty::Entity as Counterparty;
+pub use super::exchange_rate::Entity as ExchangeRate;
+pub use super::space::Entity as Space;
+pub use super::sync_state::Entity as SyncState;
 pub use super::transaction::Entity as Transaction;
@@ -9,18 +9,69 @@ pub struct Model {
     pub id: i32,
     pub uid: String,
     pub account_uid: String,
+    /// The feed category: the account's default category or one of its spaces
+    pub category_uid: String,
     pub transaction_time: DateTimeUtc,
     pub counterparty_id: i32,
-    #[sea_orm(column_type = "Float")]
-    pub amount: f32,
+    /// Signed amount in minor units of `currency`
+    pub amount: i64,
     pub currency: String,
+    /// Signed amount in minor units of `source_currency`, for purchases in another currency
+    pub source_amount: Option<i64>,
+    pub source_currency: Option<String>,
     pub spending_category: String,
     pub reference: String,
     pub user_note: String,
     pub status: String,
+    /// The merchant category code of a card payment
+    pub mcc: Option<i32>,
+    pub transacting_user_uid: Option<String>,
+    pub settlement_time: Option<DateTimeUtc>,
+    pub updated_at: Option<DateTimeUtc>,
+    pub source: Option<String>,
+    pub country: Option<String>,
+    /// Minor units rounded up from this payment into the savings goal `round_up_goal_uid`
+    pub round_up_amount: Option<i64>,
+    pub round_up_goal_uid: Option<String>,
+    pub has_attachment: bool,
+    pub has_receipt: bool,
+    pub batch_payment_uid: Option<String>,
+    pub batch_payment_type: Option<String>,
+    /// The feed item as received from Starling, as JSON
+    #[sea_orm(column_type = "Text", nullable)]
+    pub raw: Option<String>,
+    /// The stored account the counterparty is, for transfers between own accounts
+    pub counterparty_account_uid: Option<String>,
+    /// The uid of the other leg of a transfer between own accounts
+    pub transfer_uid: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::account::Entity",
+        from = "Column::AccountUid",
+        to = "super::account::Column::Uid"
+    )]
+    Account,
+    #[sea_orm(
+        belongs_to = "super::counterparty::Entity",
+        from = "Column::CounterpartyId",
+        to = "super::counterparty::Column::Id"
+    )]
+    Counterparty,
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl Related<super::counterparty::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Counterparty.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,106 @@
+//! Write transactions as a [beancount](https://beancount.github.io/docs/beancount_language_syntax.html) file
+
+use super::{bank_accounts, ExportRecord, Exporter};
+use crate::money::Money;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Writes a beancount file.
+///
+/// An `open` directive is written for every account used, dated from the creation of the
+/// Starling account it belongs to, with the account's bank details as metadata where they're known.
+/// Transactions carry the Starling feed item uid as metadata, and legs of a transfer between own
+/// accounts the uid of the other leg as `transfer`.
+pub struct Beancount;
+
+impl Exporter for Beancount {
+    fn write(&self, records: &[ExportRecord], out: &mut dyn Write) -> io::Result<()> {
+        let records: Vec<&ExportRecord> = records.iter().filter(|r| r.is_exportable()).collect();
+
+        let mut opened = BTreeMap::new();
+        for record in records.iter() {
+            let opened_at = record.account_created_at.format("%Y-%m-%d").to_string();
+            for account in [record.asset_account(), record.category_account()] {
+                opened.entry(account).or_insert_with(|| opened_at.clone());
+            }
+        }
+        let details = bank_accounts(records.iter().copied());
+        for (account, opened_at) in opened.iter() {
+            writeln!(out, "{} open {}", opened_at, account)?;
+            for (name, value) in details.get(account).map(|d| d.tags()).unwrap_or_default() {
+                writeln!(out, "  {}: \"{}\"", name, escape(value))?;
+            }
+        }
+        if !opened.is_empty() {
+            writeln!(out)?;
+        }
+
+        for record in records {
+            write_transaction(record, out)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_transaction(record: &ExportRecord, out: &mut dyn Write) -> io::Result<()> {
+    let transaction = &record.transaction;
+    let flag = if record.is_cleared() { "*" } else { "!" };
+
+    writeln!(
+        out,
+        "{} {} \"{}\" \"{}\"",
+        transaction.transaction_time.format("%Y-%m-%d"),
+        flag,
+        escape(&record.counterparty_name),
+        escape(record.narration())
+    )?;
+    writeln!(out, "  uid: \"{}\"", escape(&transaction.uid))?;
+    if let Some(transfer_uid) = &transaction.transfer_uid {
+        writeln!(out, "  transfer: \"{}\"", escape(transfer_uid))?;
+    }
+    write_posting(out, &record.category_account(), &-record.amount())?;
+    write_posting(out, &record.asset_account(), &record.amount())?;
+    writeln!(out)
+}
+
+fn write_posting(out: &mut dyn Write, account: &str, amount: &Money) -> io::Result<()> {
+    writeln!(
+        out,
+        "  {:<40}  {:>12} {}",
+        account,
+        amount.to_decimal_string(),
+        amount.currency
+    )
+}
+
+// Escape a string for use inside beancount double quotes
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ledger::tests::record;
+
+    #[test]
+    fn it_opens_accounts_and_writes_metadata() {
+        let mut out = Vec::new();
+        Beancount
+            .write(&[record(-1250, "Settled")], &mut out)
+            .unwrap();
+        let journal = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            journal,
+            "2019-06-01 open Assets:Starling:Personal\n\
+             2019-06-01 open Expenses:Personal:EatingOut\n\
+             \n\
+             2023-05-01 * \"Pizza Place\" \"CARD 1234\"\n  \
+             uid: \"feed-uid\"\n  \
+             Expenses:Personal:EatingOut                      12.50 GBP\n  \
+             Assets:Starling:Personal                        -12.50 GBP\n\n"
+        );
+    }
+}
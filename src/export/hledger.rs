@@ -0,0 +1,70 @@
+//! Write transactions as an [hledger](https://hledger.org/hledger.html#journal) journal
+
+use super::{
+    ledger::{write_account_declarations, write_posting},
+    ExportRecord, Exporter,
+};
+use std::io::{self, Write};
+
+/// Writes an hledger journal.
+///
+/// Postings match the ledger format; the header uses ISO dates, `payee | note` descriptions
+/// and an hledger `uid:` tag so transactions can be queried with `tag:uid=...`; legs of a transfer
+/// between own accounts also have a `transfer:` tag with the uid of the other leg. Asset accounts
+/// are declared as in the ledger format.
+pub struct Hledger;
+
+impl Exporter for Hledger {
+    fn write(&self, records: &[ExportRecord], out: &mut dyn Write) -> io::Result<()> {
+        write_account_declarations(records, out)?;
+        for record in records.iter().filter(|r| r.is_exportable()) {
+            write_transaction(record, out)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_transaction(record: &ExportRecord, out: &mut dyn Write) -> io::Result<()> {
+    let transaction = &record.transaction;
+    let state = if record.is_cleared() { "*" } else { "!" };
+
+    write!(
+        out,
+        "{} {} {}",
+        transaction.transaction_time.format("%Y-%m-%d"),
+        state,
+        record.counterparty_name.replace('|', "/")
+    )?;
+    if !record.narration().is_empty() {
+        write!(out, " | {}", record.narration())?;
+    }
+    match &transaction.transfer_uid {
+        Some(transfer_uid) => writeln!(
+            out,
+            "  ; uid:{}, transfer:{}",
+            transaction.uid, transfer_uid
+        )?,
+        None => writeln!(out, "  ; uid:{}", transaction.uid)?,
+    }
+    write_posting(out, &record.category_account(), &-record.amount())?;
+    write_posting(out, &record.asset_account(), &record.amount())?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ledger::tests::record;
+
+    #[test]
+    fn it_writes_payee_and_note() {
+        let mut out = Vec::new();
+        Hledger
+            .write(&[record(-1250, "Pending")], &mut out)
+            .unwrap();
+        let journal = String::from_utf8(out).unwrap();
+
+        assert!(journal.starts_with("2023-05-01 ! Pizza Place | CARD 1234  ; uid:feed-uid\n"));
+    }
+}
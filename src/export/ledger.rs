@@ -0,0 +1,190 @@
+//! Write transactions as a [ledger-cli](https://ledger-cli.org/doc/ledger3.html) journal
+
+use super::{bank_accounts, ExportRecord, Exporter};
+use crate::money::Money;
+use std::io::{self, Write};
+
+/// Writes a ledger journal.
+///
+/// Each transaction is booked against an `Expenses` or `Income` account derived from the account
+/// name and spending category, and balanced against the Starling asset account. Both legs of a
+/// transfer between own accounts or to a space are booked against `Assets:Transfers` instead,
+/// with the uid of the other leg of a transfer between accounts as a comment. Asset accounts
+/// whose bank details are known are declared first, with the details as comments.
+pub struct Ledger;
+
+impl Exporter for Ledger {
+    fn write(&self, records: &[ExportRecord], out: &mut dyn Write) -> io::Result<()> {
+        write_account_declarations(records, out)?;
+        for record in records.iter().filter(|r| r.is_exportable()) {
+            write_transaction(record, out)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_transaction(record: &ExportRecord, out: &mut dyn Write) -> io::Result<()> {
+    let transaction = &record.transaction;
+    let state = if record.is_cleared() { "*" } else { "!" };
+
+    writeln!(
+        out,
+        "{} {} {}",
+        transaction.transaction_time.format("%Y/%m/%d"),
+        state,
+        record.counterparty_name
+    )?;
+    writeln!(out, "    ; uid: {}", transaction.uid)?;
+    if let Some(transfer_uid) = &transaction.transfer_uid {
+        writeln!(out, "    ; transfer: {}", transfer_uid)?;
+    }
+    if !transaction.reference.is_empty() {
+        writeln!(out, "    ; reference: {}", transaction.reference)?;
+    }
+    if !transaction.user_note.is_empty() {
+        writeln!(out, "    ; note: {}", transaction.user_note)?;
+    }
+    write_posting(out, &record.category_account(), &-record.amount())?;
+    write_posting(out, &record.asset_account(), &record.amount())?;
+    writeln!(out)
+}
+
+/// Declare the asset accounts that have bank details, shared with the hledger format
+pub(super) fn write_account_declarations(
+    records: &[ExportRecord],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let accounts = bank_accounts(records);
+    for (account, details) in accounts.iter() {
+        writeln!(out, "account {}", account)?;
+        for (name, value) in details.tags() {
+            writeln!(out, "    ; {}: {}", name, value)?;
+        }
+    }
+    if !accounts.is_empty() {
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Write a posting line, shared with the hledger format
+pub(super) fn write_posting(out: &mut dyn Write, account: &str, amount: &Money) -> io::Result<()> {
+    writeln!(
+        out,
+        "    {:<40}  {:>12} {}",
+        account,
+        amount.to_decimal_string(),
+        amount.currency
+    )
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::entities::transaction;
+    use crate::export::BankDetails;
+    use chrono::{TimeZone, Utc};
+
+    pub fn record(amount: i64, status: &str) -> ExportRecord {
+        ExportRecord {
+            transaction: transaction::Model {
+                id: 1,
+                uid: String::from("feed-uid"),
+                account_uid: String::from("account-uid"),
+                category_uid: String::from("category-uid"),
+                transaction_time: Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap(),
+                counterparty_id: 1,
+                amount,
+                currency: String::from("GBP"),
+                source_amount: None,
+                source_currency: None,
+                spending_category: String::from("EATING_OUT"),
+                reference: String::from("CARD 1234"),
+                user_note: String::new(),
+                status: String::from(status),
+                mcc: None,
+                transacting_user_uid: None,
+                settlement_time: None,
+                updated_at: None,
+                source: None,
+                country: None,
+                round_up_amount: None,
+                round_up_goal_uid: None,
+                has_attachment: false,
+                has_receipt: false,
+                batch_payment_uid: None,
+                batch_payment_type: None,
+                raw: None,
+                counterparty_account_uid: None,
+                transfer_uid: None,
+            },
+            account_name: String::from("Personal"),
+            account_created_at: Utc.with_ymd_and_hms(2019, 6, 1, 9, 0, 0).unwrap(),
+            space_name: None,
+            counterparty_name: String::from("Pizza Place"),
+            counterparty_category_uid: None,
+            bank_details: BankDetails::default(),
+        }
+    }
+
+    #[test]
+    fn it_writes_a_balanced_transaction() {
+        let mut out = Vec::new();
+        Ledger.write(&[record(-1250, "Settled")], &mut out).unwrap();
+        let journal = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            journal,
+            "2023/05/01 * Pizza Place\n    \
+             ; uid: feed-uid\n    \
+             ; reference: CARD 1234\n    \
+             Expenses:Personal:EatingOut                      12.50 GBP\n    \
+             Assets:Starling:Personal                        -12.50 GBP\n\n"
+        );
+    }
+
+    #[test]
+    fn it_declares_accounts_with_bank_details() {
+        let mut record = record(-1250, "Settled");
+        record.bank_details.sort_code = Some(String::from("608371"));
+        record.bank_details.account_number = Some(String::from("12345678"));
+        let mut out = Vec::new();
+        Ledger.write(&[record], &mut out).unwrap();
+        let journal = String::from_utf8(out).unwrap();
+
+        assert!(journal.starts_with(
+            "account Assets:Starling:Personal\n    \
+             ; sort_code: 608371\n    \
+             ; account_number: 12345678\n\n\
+             2023/05/01 * Pizza Place\n"
+        ));
+    }
+
+    #[test]
+    fn it_writes_transfers_between_own_accounts() {
+        let mut sent = record(-5000, "Settled");
+        sent.transaction.transfer_uid = Some(String::from("received-uid"));
+        sent.transaction.reference = String::new();
+        let mut out = Vec::new();
+        Ledger.write(&[sent], &mut out).unwrap();
+        let journal = String::from_utf8(out).unwrap();
+
+        assert!(journal.contains(
+            "    ; uid: feed-uid\n    \
+             ; transfer: received-uid\n    \
+             Assets:Transfers                                 50.00 GBP\n    \
+             Assets:Starling:Personal                        -50.00 GBP\n"
+        ));
+    }
+
+    #[test]
+    fn it_skips_declined_transactions() {
+        let mut out = Vec::new();
+        Ledger
+            .write(&[record(-1250, "Declined")], &mut out)
+            .unwrap();
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+//! Export stored transactions to plain text accounting formats
+
+pub mod beancount;
+pub mod hledger;
+pub mod ledger;
+
+use crate::entities::{account, transaction};
+use crate::money::Money;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Implemented by each plain text accounting format
+pub trait Exporter {
+    /// Write the exportable records to `out`
+    fn write(&self, records: &[ExportRecord], out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// The account both legs of a transfer between own accounts, or between an account and one of its
+/// spaces, are booked against, so that together they balance to nothing rather than appearing as
+/// income and expense
+pub const TRANSFERS_ACCOUNT: &str = "Assets:Transfers";
+
+/// Represents the available export formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Format {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl Format {
+    /// Return the exporter that writes this format
+    pub fn exporter(&self) -> Box<dyn Exporter> {
+        match self {
+            Format::Ledger => Box::new(ledger::Ledger),
+            Format::Hledger => Box::new(hledger::Hledger),
+            Format::Beancount => Box::new(beancount::Beancount),
+        }
+    }
+}
+
+/// A stored transaction together with details of its account, space and counterparty
+#[derive(Debug)]
+pub struct ExportRecord {
+    pub transaction: transaction::Model,
+    pub account_name: String,
+    pub account_created_at: DateTime<Utc>,
+    /// The space the transaction is in, if it isn't in the account's default category
+    pub space_name: Option<String>,
+    pub counterparty_name: String,
+    /// The other feed category of a transfer between an account and one of its spaces
+    pub counterparty_category_uid: Option<String>,
+    pub bank_details: BankDetails,
+}
+
+/// Represents the bank identifiers of a Starling account, where they're known
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BankDetails {
+    pub sort_code: Option<String>,
+    pub account_number: Option<String>,
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+}
+
+impl BankDetails {
+    pub fn from_account(account: &account::Model) -> Self {
+        Self {
+            sort_code: account.sort_code.clone(),
+            account_number: account.account_number.clone(),
+            iban: account.iban.clone(),
+            bic: account.bic.clone(),
+        }
+    }
+
+    /// The known identifiers, named for use as account metadata
+    pub fn tags(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("sort_code", &self.sort_code),
+            ("account_number", &self.account_number),
+            ("iban", &self.iban),
+            ("bic", &self.bic),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (name, value)))
+        .collect()
+    }
+}
+
+/// The Starling asset accounts of the records that have bank details, with their details
+pub fn bank_accounts<'a>(
+    records: impl IntoIterator<Item = &'a ExportRecord>,
+) -> BTreeMap<String, &'a BankDetails> {
+    records
+        .into_iter()
+        .filter(|record| !record.bank_details.tags().is_empty())
+        .map(|record| (record.bank_account(), &record.bank_details))
+        .collect()
+}
+
+impl ExportRecord {
+    /// Return false for transactions that never moved money (declined, reversed, upcoming)
+    pub fn is_exportable(&self) -> bool {
+        matches!(self.transaction.status.as_str(), "Settled" | "Pending")
+    }
+
+    /// Return true if the transaction has settled
+    pub fn is_cleared(&self) -> bool {
+        self.transaction.status == "Settled"
+    }
+
+    /// The narration for the transaction: the user's note if there is one, else the reference
+    pub fn narration(&self) -> &str {
+        if self.transaction.user_note.is_empty() {
+            &self.transaction.reference
+        } else {
+            &self.transaction.user_note
+        }
+    }
+
+    /// The signed amount of the transaction
+    pub fn amount(&self) -> Money {
+        Money::new(self.transaction.amount, &self.transaction.currency)
+    }
+
+    /// Return true if the transaction is a leg of a transfer between own accounts, or between an
+    /// account and one of its spaces
+    pub fn is_transfer(&self) -> bool {
+        self.transaction.transfer_uid.is_some() || self.counterparty_category_uid.is_some()
+    }
+
+    /// The account path the transaction is booked against, e.g. `Expenses:Personal:EatingOut`,
+    /// or `TRANSFERS_ACCOUNT` for a transfer
+    pub fn category_account(&self) -> String {
+        if self.is_transfer() {
+            return TRANSFERS_ACCOUNT.to_string();
+        }
+        let root = if self.transaction.amount < 0 {
+            "Expenses"
+        } else {
+            "Income"
+        };
+        format!(
+            "{}:{}:{}",
+            root,
+            account_component(&self.account_name),
+            account_component(&self.transaction.spending_category)
+        )
+    }
+
+    /// The asset account the money moved in or out of, e.g. `Assets:Starling:Personal`, or
+    /// `Assets:Starling:Personal:Holiday` for a transaction in a space
+    pub fn asset_account(&self) -> String {
+        let account = self.bank_account();
+        match &self.space_name {
+            Some(space) => format!("{}:{}", account, account_component(space)),
+            None => account,
+        }
+    }
+
+    /// The asset account of the Starling account itself, outside any space
+    pub fn bank_account(&self) -> String {
+        format!("Assets:Starling:{}", account_component(&self.account_name))
+    }
+}
+
+/// Convert a name such as `EATING_OUT` or `Joint account` to an account path component
+/// (`EatingOut`, `JointAccount`) that is valid in ledger, hledger and beancount.
+pub fn account_component(name: &str) -> String {
+    let component: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let word = word.to_lowercase();
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+
+    if component.is_empty() {
+        String::from("Unknown")
+    } else {
+        component
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_formats() {
+        assert_eq!("beancount".parse::<Format>().unwrap(), Format::Beancount);
+        assert!("csv".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn it_books_space_transactions_to_a_sub_account() {
+        let mut record = ledger::tests::record(10000, "Settled");
+        assert_eq!(record.asset_account(), "Assets:Starling:Personal");
+
+        record.space_name = Some(String::from("Holiday fund"));
+        assert_eq!(
+            record.asset_account(),
+            "Assets:Starling:Personal:HolidayFund"
+        );
+    }
+
+    #[test]
+    fn it_books_transfers_to_the_transfers_account() {
+        let mut record = ledger::tests::record(-5000, "Settled");
+        assert_eq!(record.category_account(), "Expenses:Personal:EatingOut");
+
+        record.transaction.transfer_uid = Some(String::from("other-leg-uid"));
+        assert!(record.is_transfer());
+        assert_eq!(record.category_account(), TRANSFERS_ACCOUNT);
+    }
+
+    #[test]
+    fn it_books_transfers_to_spaces_to_the_transfers_account() {
+        let mut record = ledger::tests::record(-10000, "Settled");
+        record.transaction.spending_category = String::from("SAVING");
+        assert_eq!(record.category_account(), "Expenses:Personal:Saving");
+
+        record.counterparty_category_uid = Some(String::from("space-uid"));
+        assert!(record.is_transfer());
+        assert_eq!(record.category_account(), TRANSFERS_ACCOUNT);
+    }
+
+    #[test]
+    fn it_makes_account_components() {
+        assert_eq!(account_component("EATING_OUT"), "EatingOut");
+        assert_eq!(account_component("Joint account"), "JointAccount");
+        assert_eq!(account_component("--"), "Unknown");
+    }
+}
@@ -1,5 +1,9 @@
 pub mod commands;
 pub mod config;
+pub mod crypto;
+pub mod currency;
 pub mod db;
 pub mod entities;
+pub mod export;
+pub mod money;
 pub mod starling;
@@ -0,0 +1,154 @@
+//! Exact monetary amounts, held as an integer number of minor units (e.g. pennies)
+//!
+//! Amounts are never converted to floating point, so sums and formatting are exact. Only
+//! `convert`, which applies an exchange rate, rounds.
+
+use crate::currency;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Neg;
+
+/// Represents an amount of money in a currency
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    #[serde(rename = "minorUnits")]
+    pub minor_units: i64,
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(minor_units: i64, currency: &str) -> Self {
+        Self {
+            minor_units,
+            currency: currency.to_string(),
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor_units < 0
+    }
+
+    /// Add two amounts, returning `None` if the currencies differ or the sum overflows
+    pub fn checked_add(&self, other: &Money) -> Option<Money> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(Money::new(
+            self.minor_units.checked_add(other.minor_units)?,
+            &self.currency,
+        ))
+    }
+
+    /// Convert to another currency at `rate` units of `currency` per unit of this one, rounding
+    /// to the nearest minor unit
+    pub fn convert(&self, currency: &str, rate: f64) -> Money {
+        let shift = currency::exponent(currency) as i32 - currency::exponent(&self.currency) as i32;
+        let minor_units = self.minor_units as f64 * rate * 10f64.powi(shift);
+        Money::new(minor_units.round() as i64, currency)
+    }
+
+    /// The amount in major units, e.g. `-1234.56`
+    pub fn to_decimal_string(&self) -> String {
+        let (sign, major, minor) = self.parts();
+        format!("{}{}{}", sign, major, minor)
+    }
+
+    /// The amount in major units with thousands separators, e.g. `-1,234.56`
+    pub fn to_grouped_string(&self) -> String {
+        let (sign, major, minor) = self.parts();
+        let digits = major.to_string();
+        let mut grouped = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+        format!("{}{}{}", sign, grouped, minor)
+    }
+
+    // Split into sign, major units and the minor units formatted with their decimal point, if the
+    // currency has minor units
+    fn parts(&self) -> (&'static str, u64, String) {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let units = self.minor_units.unsigned_abs();
+        let exponent = currency::exponent(&self.currency);
+        let scale = 10u64.pow(exponent);
+        let minor = match exponent {
+            0 => String::new(),
+            _ => format!(".{:0width$}", units % scale, width = exponent as usize),
+        };
+        (sign, units / scale, minor)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money::new(-self.minor_units, &self.currency)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.currency, self.to_grouped_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_formats_without_rounding() {
+        // 16_777_217 pennies can't be represented exactly as an f32
+        let money = Money::new(16_777_217, "GBP");
+        assert_eq!(money.to_decimal_string(), "167772.17");
+        assert_eq!(money.to_grouped_string(), "167,772.17");
+        assert_eq!(money.to_string(), "GBP 167,772.17");
+    }
+
+    #[test]
+    fn it_formats_negative_and_small_amounts() {
+        assert_eq!(Money::new(-5, "GBP").to_decimal_string(), "-0.05");
+        assert_eq!(Money::new(-123_456, "GBP").to_grouped_string(), "-1,234.56");
+        assert_eq!(Money::new(0, "GBP").to_decimal_string(), "0.00");
+        assert_eq!(
+            Money::new(i64::MIN, "GBP").to_decimal_string(),
+            "-92233720368547758.08"
+        );
+    }
+
+    #[test]
+    fn it_formats_with_each_currencys_exponent() {
+        assert_eq!(Money::new(123_456, "JPY").to_string(), "JPY 123,456");
+        assert_eq!(
+            Money::new(-1_234_567, "KWD").to_decimal_string(),
+            "-1234.567"
+        );
+        assert_eq!(Money::new(5, "BHD").to_decimal_string(), "0.005");
+    }
+
+    #[test]
+    fn it_converts_between_currencies() {
+        assert_eq!(
+            Money::new(10_000, "EUR").convert("GBP", 0.86),
+            Money::new(8_600, "GBP")
+        );
+        assert_eq!(
+            Money::new(-1_000, "GBP").convert("JPY", 180.456),
+            Money::new(-1_805, "JPY")
+        );
+    }
+
+    #[test]
+    fn it_adds_matching_currencies_only() {
+        let a = Money::new(10, "GBP");
+        assert_eq!(
+            a.checked_add(&Money::new(-25, "GBP")),
+            Some(Money::new(-15, "GBP"))
+        );
+        assert_eq!(a.checked_add(&Money::new(10, "USD")), None);
+    }
+}
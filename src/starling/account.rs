@@ -1,19 +1,17 @@
 //! Structures and methods for processing `/api/v2/accounts/` endpoints
 
-use super::client::StarlingApiClient;
-use anyhow::Result;
+use crate::money::Money;
 use chrono::{DateTime, Utc};
-use format_num::NumberFormat;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// Represents a list of Starling accounts
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Accounts {
     pub accounts: Vec<Account>,
 }
 
 /// Represents a Starling account
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Account {
     #[serde(rename = "accountUid")]
     pub uid: String,
@@ -23,8 +21,39 @@ pub struct Account {
     pub default_category: String,
     pub name: String,
 }
+
+/// Represents the holder of a token's accounts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountHolder {
+    #[serde(rename = "accountHolderUid")]
+    pub uid: String,
+    /// e.g. `INDIVIDUAL`, `JOINT` or `BUSINESS`
+    #[serde(rename = "accountHolderType")]
+    pub holder_type: String,
+}
+
+/// Represents the name of an account holder
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountHolderName {
+    #[serde(rename = "accountHolderName")]
+    pub name: String,
+}
+
+/// Represents the bank identifiers of an account
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountIdentifiers {
+    /// The account number
+    #[serde(rename = "accountIdentifier")]
+    pub account_identifier: String,
+    /// The sort code
+    #[serde(rename = "bankIdentifier")]
+    pub bank_identifier: String,
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+}
+
 /// Represents a Starling Balance response
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Balance {
     #[serde(rename = "clearedBalance")]
     pub cleared: SignedCurrencyAndAmount,
@@ -40,38 +69,23 @@ pub struct Balance {
     pub total_effective: SignedCurrencyAndAmount,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SignedCurrencyAndAmount {
-    currency: String,
+    pub currency: String,
     #[serde(rename = "minorUnits")]
-    minor_units: i64,
+    pub minor_units: i64,
 }
 
 impl SignedCurrencyAndAmount {
-    pub fn as_float(&self) -> f32 {
-        self.minor_units as f32 / 100.0
+    pub fn as_money(&self) -> Money {
+        Money::new(self.minor_units, &self.currency)
     }
 
     pub fn as_string(&self) -> String {
-        let num = NumberFormat::new();
-        let amount = num.format(" >10,.2f", self.as_float());
-        format!("{} {}", self.currency, amount)
-    }
-}
-
-// Implement `/api/v2/accounts/{accountUid}/balance`
-//
-impl StarlingApiClient {
-    pub async fn balance(&self, account_uid: &String) -> Result<Balance> {
-        let mut resp = surf::get(format!(
-            "{}/accounts/{}/balance",
-            &self.base_url, &account_uid
-        ))
-        .header("Accept", "application/json")
-        .header("Authorization", format!("Bearer {}", &self.key))
-        .await
-        .unwrap();
-
-        Ok(resp.body_json::<Balance>().await.unwrap())
+        format!(
+            "{} {:>10}",
+            self.currency,
+            self.as_money().to_grouped_string()
+        )
     }
 }
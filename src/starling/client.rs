@@ -1,51 +1,176 @@
 //! Handles querying the Starling API and converting results into `Transaction` objects.
 
 use super::{
-    account::{Account, Accounts},
-    transaction::{StarlingTransaction, StarlingTransactions},
+    account::{Account, AccountHolder, AccountHolderName, AccountIdentifiers, Accounts, Balance},
+    error::StarlingError,
+    identity::TokenIdentity,
+    retry::{parse_retry_after, RetryPolicy},
+    space::{SavingsGoal, SavingsGoals, Spaces},
+    transaction::{StarlingTransaction, StarlingTransactions, Status},
 };
+use crate::config::{Environment, StarlingConfig};
+use crate::crypto::{CryptoError, TokenKey};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 
 #[async_trait::async_trait]
 pub trait StarlingClient {
-    async fn accounts(&self) -> Vec<Account>;
+    async fn accounts(&self) -> Result<Vec<Account>, StarlingError>;
+    async fn balance(&self, account_uid: &str) -> Result<Balance, StarlingError>;
     async fn transactions_since(
         &self,
         account_uid: &str,
         category: &str,
-        since: chrono::Duration,
-    ) -> Vec<StarlingTransaction>;
+        since: DateTime<Utc>,
+    ) -> Result<StarlingTransactions, StarlingError>;
+    async fn transactions_between(
+        &self,
+        account_uid: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<StarlingTransactions, StarlingError>;
+    async fn spaces(&self, account_uid: &str) -> Result<Spaces, StarlingError>;
+    async fn savings_goals(&self, account_uid: &str) -> Result<Vec<SavingsGoal>, StarlingError>;
+    async fn token_identity(&self) -> Result<TokenIdentity, StarlingError>;
+    async fn account_holder(&self) -> Result<AccountHolder, StarlingError>;
+    async fn account_holder_name(&self) -> Result<String, StarlingError>;
+    async fn account_identifiers(
+        &self,
+        account_uid: &str,
+    ) -> Result<AccountIdentifiers, StarlingError>;
     async fn default_category(&self);
 }
 
+/// The longest time range requested from `settled-transactions-between` in a single call
+pub const SETTLED_WINDOW_DAYS: i64 = 90;
+
 // API client /////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Represents a single Starling account
 pub struct StarlingApiClient {
     pub key: String,
     pub base_url: String,
+    pub retry: RetryPolicy,
 }
 
 impl StarlingApiClient {
     pub fn new(api_key: &String) -> Self {
         Self {
             key: api_key.to_owned(),
-            base_url: "https://api.starlingbank.com/api/v2".to_string(),
+            base_url: Environment::Production.base_url().to_string(),
+            retry: RetryPolicy::default(),
         }
     }
-}
 
-#[async_trait::async_trait]
-impl StarlingClient for StarlingApiClient {
-    async fn accounts(&self) -> Vec<Account> {
-        let mut resp = surf::get(format!("{}/accounts", &self.base_url))
+    /// Create a client using the Starling settings from the config file
+    pub fn from_config(api_key: &String, config: &StarlingConfig) -> Self {
+        Self {
+            key: api_key.to_owned(),
+            base_url: config.base_url(),
+            retry: config.retry.clone(),
+        }
+    }
+
+    /// Create a client for a stored account, decrypting its access token with `key`
+    pub fn from_stored_token(
+        stored_token: &str,
+        key: &TokenKey,
+        config: &StarlingConfig,
+    ) -> Result<Self, CryptoError> {
+        let token = key.decrypt(stored_token)?;
+
+        Ok(Self::from_config(&token, config))
+    }
+
+    /// GET `path` and decode the JSON response
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, StarlingError> {
+        let url = format!("{}{}", &self.base_url, path);
+        self.send(&url, || Ok(surf::get(&url))).await
+    }
+
+    /// GET `path` with the given query parameters and decode the JSON response
+    async fn get_with_query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &(impl Serialize + Sync),
+    ) -> Result<T, StarlingError> {
+        let url = format!("{}{}", &self.base_url, path);
+        self.send(&url, || {
+            surf::get(&url)
+                .query(query)
+                .map_err(|e| StarlingError::Network(e.to_string()))
+        })
+        .await
+    }
+
+    /// Send the request built by `request`, retrying transient failures according to the policy
+    async fn send<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        request: impl Fn() -> Result<surf::RequestBuilder, StarlingError>,
+    ) -> Result<T, StarlingError> {
+        let mut attempt = 0;
+        loop {
+            match self.send_once(url, request()?).await {
+                Err(error) => match self.retry.delay(attempt, &error) {
+                    Some(delay) => {
+                        attempt += 1;
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(error),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_once<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        request: surf::RequestBuilder,
+    ) -> Result<T, StarlingError> {
+        let mut resp = request
             .header("Accept", "application/json")
             .header("Authorization", format!("Bearer {}", &self.key))
             .await
-            .unwrap();
+            .map_err(|e| StarlingError::Network(e.to_string()))?;
+
+        let body = resp
+            .body_string()
+            .await
+            .map_err(|e| StarlingError::Network(e.to_string()))?;
 
-        resp.body_json::<Accounts>().await.unwrap().accounts
+        if !resp.status().is_success() {
+            let retry_after = resp
+                .header("Retry-After")
+                .and_then(|value| parse_retry_after(value.as_str()));
+            return Err(StarlingError::from_status(
+                resp.status().into(),
+                url,
+                &body,
+                retry_after,
+            ));
+        }
+
+        serde_json::from_str(&body).map_err(|e| StarlingError::decode(e, &body))
+    }
+}
+
+#[async_trait::async_trait]
+impl StarlingClient for StarlingApiClient {
+    async fn accounts(&self) -> Result<Vec<Account>, StarlingError> {
+        let accounts: Accounts = self.get("/accounts").await?;
+
+        Ok(accounts.accounts)
+    }
+
+    /// Get an account's balance
+    /// /api/v2/accounts/{accountUid}/balance
+    async fn balance(&self, account_uid: &str) -> Result<Balance, StarlingError> {
+        self.get(&format!("/accounts/{}/balance", account_uid))
+            .await
     }
 
     /// Get the account holder's feed items which were created or updated since a given date
@@ -54,49 +179,322 @@ impl StarlingClient for StarlingApiClient {
         &self,
         account_uid: &str,
         category: &str,
-        since: chrono::Duration,
-    ) -> Vec<StarlingTransaction> {
-        let start_date = Utc::now() - since;
-        let mut resp = surf::get(format!(
-            "{}/feed/account/{}/category/{}",
-            &self.base_url, account_uid, category
-        ))
-        .header("Accept", "application/json")
-        .header("Authorization", format!("Bearer {}", &self.key))
-        .query(&Query {
-            changes_since: start_date,
-        })
-        .unwrap()
+        since: DateTime<Utc>,
+    ) -> Result<StarlingTransactions, StarlingError> {
+        self.get_with_query(
+            &format!("/feed/account/{}/category/{}", account_uid, category),
+            &Query {
+                changes_since: since,
+            },
+        )
         .await
-        .unwrap();
+    }
+
+    /// Get the account holder's settled feed items between two timestamps, across all categories
+    /// /api/v2/feed/account/{accountUid}/settled-transactions-between
+    ///
+    /// Long ranges are fetched in windows of `SETTLED_WINDOW_DAYS`.
+    async fn transactions_between(
+        &self,
+        account_uid: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<StarlingTransactions, StarlingError> {
+        let mut transactions = StarlingTransactions::default();
+        for (start, end) in windows(from, to, chrono::Duration::days(SETTLED_WINDOW_DAYS)) {
+            let window: StarlingTransactions = self
+                .get_with_query(
+                    &format!("/feed/account/{}/settled-transactions-between", account_uid),
+                    &BetweenQuery {
+                        min_transaction_timestamp: start,
+                        max_transaction_timestamp: end,
+                    },
+                )
+                .await?;
+            transactions.append(window);
+        }
+
+        Ok(transactions)
+    }
+
+    /// Get an account's savings goals and spending spaces
+    /// /api/v2/account/{accountUid}/spaces
+    async fn spaces(&self, account_uid: &str) -> Result<Spaces, StarlingError> {
+        self.get(&format!("/account/{}/spaces", account_uid)).await
+    }
+
+    /// Get an account's savings goals
+    /// /api/v2/account/{accountUid}/savings-goals
+    async fn savings_goals(&self, account_uid: &str) -> Result<Vec<SavingsGoal>, StarlingError> {
+        let goals: SavingsGoals = self
+            .get(&format!("/account/{}/savings-goals", account_uid))
+            .await?;
+
+        Ok(goals.savings_goal_list)
+    }
+
+    /// Get the account holder, scopes and expiry of the client's access token
+    /// /api/v2/identity/token
+    async fn token_identity(&self) -> Result<TokenIdentity, StarlingError> {
+        self.get("/identity/token").await
+    }
+
+    /// Get the holder of the client's accounts
+    /// /api/v2/account-holder
+    async fn account_holder(&self) -> Result<AccountHolder, StarlingError> {
+        self.get("/account-holder").await
+    }
+
+    /// Get the name of the holder of the client's accounts
+    /// /api/v2/account-holder/name
+    async fn account_holder_name(&self) -> Result<String, StarlingError> {
+        let name: AccountHolderName = self.get("/account-holder/name").await?;
+
+        Ok(name.name)
+    }
 
-        resp.body_json::<StarlingTransactions>()
+    /// Get an account's sort code, account number, IBAN and BIC
+    /// /api/v2/accounts/{accountUid}/identifiers
+    async fn account_identifiers(
+        &self,
+        account_uid: &str,
+    ) -> Result<AccountIdentifiers, StarlingError> {
+        self.get(&format!("/accounts/{}/identifiers", account_uid))
             .await
-            .unwrap()
-            .feed_items
     }
 
     async fn default_category(&self) {}
 }
 
+/// Split the range `[from, to)` into consecutive windows no longer than `size`
+pub fn windows(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    size: chrono::Duration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut windows = Vec::new();
+    let mut start = from;
+    while start < to {
+        let end = std::cmp::min(start + size, to);
+        windows.push((start, end));
+        start = end;
+    }
+    windows
+}
+
 // Mock Client for testing /////////////////////////////////////////////////////////////////////////////////////
 
-/// A structure for testing
-struct StarlingMockClient;
+/// A fake client serving accounts, balances and feed items from memory, so that code using a
+/// `StarlingClient` can be tested without a network.
+///
+/// Build one with the `with_*` methods, or load it from a JSON fixture file of the form
+/// `{"accounts": [...], "balances": {accountUid: ...}, "spaces": {accountUid: ...},
+/// "feedItems": {accountUid: [...]}, "identity": {...}, "accountHolder": {...},
+/// "accountHolderName": {...}, "identifiers": {accountUid: ...}}` where each value is shaped like
+/// the corresponding Starling API response. Feed items without a `categoryUid` belong to the
+/// account's default category.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StarlingMockClient {
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+    #[serde(default)]
+    pub balances: BTreeMap<String, Balance>,
+    #[serde(default)]
+    pub spaces: BTreeMap<String, Spaces>,
+    #[serde(default, rename = "feedItems")]
+    pub feed_items: BTreeMap<String, Vec<StarlingTransaction>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<TokenIdentity>,
+    #[serde(
+        default,
+        rename = "accountHolder",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account_holder: Option<AccountHolder>,
+    #[serde(
+        default,
+        rename = "accountHolderName",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account_holder_name: Option<AccountHolderName>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub identifiers: BTreeMap<String, AccountIdentifiers>,
+}
+
+impl StarlingMockClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a client from a JSON fixture file
+    pub fn from_fixture(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Write the client's data as a JSON fixture file
+    pub fn save_fixture(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    pub fn with_account(mut self, account: Account) -> Self {
+        self.accounts.push(account);
+        self
+    }
+
+    pub fn with_balance(mut self, account_uid: &str, balance: Balance) -> Self {
+        self.balances.insert(account_uid.to_string(), balance);
+        self
+    }
+
+    pub fn with_spaces(mut self, account_uid: &str, spaces: Spaces) -> Self {
+        self.spaces.insert(account_uid.to_string(), spaces);
+        self
+    }
+
+    pub fn with_identity(mut self, identity: TokenIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    pub fn with_account_holder(mut self, holder: AccountHolder, name: &str) -> Self {
+        self.account_holder = Some(holder);
+        self.account_holder_name = Some(AccountHolderName {
+            name: name.to_string(),
+        });
+        self
+    }
+
+    pub fn with_identifiers(mut self, account_uid: &str, identifiers: AccountIdentifiers) -> Self {
+        self.identifiers
+            .insert(account_uid.to_string(), identifiers);
+        self
+    }
+
+    pub fn with_feed_items(mut self, account_uid: &str, items: Vec<StarlingTransaction>) -> Self {
+        self.feed_items
+            .entry(account_uid.to_string())
+            .or_default()
+            .extend(items);
+        self
+    }
+
+    // A known account
+    fn account(&self, account_uid: &str) -> Result<&Account, StarlingError> {
+        self.accounts
+            .iter()
+            .find(|account| account.uid == account_uid)
+            .ok_or_else(|| StarlingError::NotFound {
+                url: format!("/accounts/{}", account_uid),
+            })
+    }
+
+    // The feed items of a known account
+    fn items(&self, account_uid: &str) -> Result<&Vec<StarlingTransaction>, StarlingError> {
+        self.account(account_uid)?;
+        Ok(self.feed_items.get(account_uid).unwrap_or(&NO_ITEMS))
+    }
+}
+
+static NO_ITEMS: Vec<StarlingTransaction> = Vec::new();
 
 #[async_trait::async_trait]
 impl StarlingClient for StarlingMockClient {
-    async fn accounts(&self) -> Vec<Account> {
-        vec![]
+    async fn accounts(&self) -> Result<Vec<Account>, StarlingError> {
+        Ok(self.accounts.clone())
+    }
+
+    async fn balance(&self, account_uid: &str) -> Result<Balance, StarlingError> {
+        self.balances
+            .get(account_uid)
+            .cloned()
+            .ok_or_else(|| StarlingError::NotFound {
+                url: format!("/accounts/{}/balance", account_uid),
+            })
     }
+
     async fn transactions_since(
         &self,
-        _account_uid: &str,
-        _category: &str,
-        _since: chrono::Duration,
-    ) -> Vec<StarlingTransaction> {
-        vec![]
+        account_uid: &str,
+        category: &str,
+        since: DateTime<Utc>,
+    ) -> Result<StarlingTransactions, StarlingError> {
+        let default_category = &self.account(account_uid)?.default_category;
+        Ok(self
+            .items(account_uid)?
+            .iter()
+            .filter(|item| item.category_uid.as_ref().unwrap_or(default_category) == category)
+            .filter(|item| item.transaction_time >= since)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into())
     }
+
+    async fn transactions_between(
+        &self,
+        account_uid: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<StarlingTransactions, StarlingError> {
+        Ok(self
+            .items(account_uid)?
+            .iter()
+            .filter(|item| matches!(item.status, Status::Settled))
+            .filter(|item| item.transaction_time >= from && item.transaction_time < to)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    async fn spaces(&self, account_uid: &str) -> Result<Spaces, StarlingError> {
+        self.account(account_uid)?;
+        Ok(self.spaces.get(account_uid).cloned().unwrap_or_default())
+    }
+
+    async fn savings_goals(&self, account_uid: &str) -> Result<Vec<SavingsGoal>, StarlingError> {
+        Ok(self.spaces(account_uid).await?.savings_goals)
+    }
+
+    /// The client's identity, or one granting every scope if none was given
+    async fn token_identity(&self) -> Result<TokenIdentity, StarlingError> {
+        Ok(self
+            .identity
+            .clone()
+            .unwrap_or_else(|| TokenIdentity::with_all_scopes("account-holder-uid")))
+    }
+
+    async fn account_holder(&self) -> Result<AccountHolder, StarlingError> {
+        self.account_holder
+            .clone()
+            .ok_or_else(|| StarlingError::NotFound {
+                url: String::from("/account-holder"),
+            })
+    }
+
+    async fn account_holder_name(&self) -> Result<String, StarlingError> {
+        self.account_holder_name
+            .as_ref()
+            .map(|name| name.name.to_owned())
+            .ok_or_else(|| StarlingError::NotFound {
+                url: String::from("/account-holder/name"),
+            })
+    }
+
+    async fn account_identifiers(
+        &self,
+        account_uid: &str,
+    ) -> Result<AccountIdentifiers, StarlingError> {
+        self.account(account_uid)?;
+        self.identifiers
+            .get(account_uid)
+            .cloned()
+            .ok_or_else(|| StarlingError::NotFound {
+                url: format!("/accounts/{}/identifiers", account_uid),
+            })
+    }
+
     async fn default_category(&self) {}
 }
 
@@ -106,3 +504,204 @@ struct Query {
     #[serde(rename = "changesSince")]
     changes_since: DateTime<Utc>,
 }
+
+/// Represents a time range query to the API
+#[derive(Serialize)]
+struct BetweenQuery {
+    #[serde(rename = "minTransactionTimestamp")]
+    min_transaction_timestamp: DateTime<Utc>,
+    #[serde(rename = "maxTransactionTimestamp")]
+    max_transaction_timestamp: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::fixture_client;
+    use crate::starling::transaction::Direction;
+    use chrono::{Duration, TimeZone};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ACCOUNTS: &str = r#"{"accounts": [{
+        "accountUid": "account-uid",
+        "createdAt": "2019-06-01T09:00:00.000Z",
+        "defaultCategory": "category-uid",
+        "name": "Personal"
+    }]}"#;
+
+    fn client(server: &MockServer, max_retries: u32) -> StarlingApiClient {
+        let config = StarlingConfig {
+            base_url: Some(server.uri()),
+            retry: RetryPolicy {
+                max_retries,
+                base_delay_ms: 1,
+                max_delay_ms: 5,
+            },
+            ..Default::default()
+        };
+        StarlingApiClient::from_config(&String::from("token"), &config)
+    }
+
+    #[tokio::test]
+    async fn it_serves_a_fixture_file() {
+        let client = fixture_client();
+
+        let accounts = client.accounts().await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        let balance = client.balance(&accounts[0].uid).await.unwrap();
+        assert_eq!(balance.effective.as_money().to_decimal_string(), "1234.56");
+
+        let since = Utc.with_ymd_and_hms(2023, 5, 2, 0, 0, 0).unwrap();
+        let items = client
+            .transactions_since(&accounts[0].uid, &accounts[0].default_category, since)
+            .await
+            .unwrap()
+            .feed_items;
+        assert_eq!(items.len(), 2);
+
+        let spaces = client.spaces(&accounts[0].uid).await.unwrap().all();
+        assert_eq!(spaces.len(), 1);
+        let goals = client.savings_goals(&accounts[0].uid).await.unwrap();
+        assert_eq!(goals.len(), 1);
+        assert_eq!(goals[0].uid, spaces[0].uid);
+        let items = client
+            .transactions_since(&accounts[0].uid, &spaces[0].uid, since)
+            .await
+            .unwrap()
+            .feed_items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].direction, Direction::In);
+
+        let from = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let items = client
+            .transactions_between(&accounts[0].uid, from, to)
+            .await
+            .unwrap()
+            .feed_items;
+        assert!(items
+            .iter()
+            .all(|item| matches!(item.status, Status::Settled)));
+
+        assert!(matches!(
+            client.balance("unknown").await,
+            Err(StarlingError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_honours_retry_after_when_rate_limited() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/accounts"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/accounts"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(ACCOUNTS))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let accounts = client(&server, 3).accounts().await.unwrap();
+        assert_eq!(accounts[0].uid, "account-uid");
+    }
+
+    #[tokio::test]
+    async fn it_gives_up_on_server_errors_after_max_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let error = client(&server, 2).accounts().await.unwrap_err();
+        assert!(matches!(error, StarlingError::Status { status: 503, .. }));
+    }
+
+    #[tokio::test]
+    async fn it_does_not_retry_auth_failures() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let error = client(&server, 5).accounts().await.unwrap_err();
+        assert!(matches!(error, StarlingError::Auth { status: 401 }));
+    }
+
+    #[tokio::test]
+    async fn it_gets_the_token_identity() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/identity/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{
+                    "accountHolderUid": "holder-uid",
+                    "authenticated": true,
+                    "expiresAt": "2023-06-01T12:00:00.000Z",
+                    "expiresInSeconds": 3600,
+                    "scopes": ["account:read", "transaction:read"]
+                }"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let identity = client(&server, 0).token_identity().await.unwrap();
+        assert_eq!(identity.account_holder_uid, "holder-uid");
+        assert_eq!(identity.scopes, ["account:read", "transaction:read"]);
+        assert_eq!(
+            identity.expires_at,
+            Some(Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn it_gets_savings_goals() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/account/account-uid/savings-goals"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"savingsGoalList": [{
+                    "savingsGoalUid": "goal-uid",
+                    "name": "Holiday",
+                    "totalSaved": { "currency": "GBP", "minorUnits": 10000 }
+                }]}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let goals = client(&server, 0)
+            .savings_goals("account-uid")
+            .await
+            .unwrap();
+        assert_eq!(goals.len(), 1);
+        assert_eq!(goals[0].name, "Holiday");
+        assert!(goals[0].target.is_none());
+    }
+
+    #[test]
+    fn it_splits_a_range_into_windows() {
+        let from = Utc.with_ymd_and_hms(2019, 1, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        let windows = windows(from, to, Duration::days(SETTLED_WINDOW_DAYS));
+
+        assert_eq!(windows.len(), 5);
+        assert_eq!(windows[0], (from, from + Duration::days(90)));
+        assert_eq!(windows[4].1, to);
+        assert!(windows.windows(2).all(|pair| pair[0].1 == pair[1].0));
+    }
+
+    #[test]
+    fn it_has_no_windows_for_an_empty_range() {
+        let from = Utc.with_ymd_and_hms(2019, 1, 1, 0, 0, 0).unwrap();
+        assert!(windows(from, from, Duration::days(1)).is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Errors returned by the Starling API client
+
+use std::time::Duration;
+use thiserror::Error;
+
+/// The number of characters of a response body kept in a `Decode` error
+const SNIPPET_LENGTH: usize = 200;
+
+/// Represents a failed request to the Starling API
+#[derive(Error, Debug)]
+pub enum StarlingError {
+    #[error("authentication failed (HTTP {status}): the access token is invalid, expired or lacks the required scope")]
+    Auth { status: u16 },
+
+    #[error("rate limited by the Starling API{}", retry_after.map(|d| format!(": retry after {}s", d.as_secs())).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("not found: {url}")]
+    NotFound { url: String },
+
+    #[error("unexpected HTTP {status} from the Starling API: {body}")]
+    Status { status: u16, body: String },
+
+    #[error("could not decode the Starling API response: {message} (body: `{body}`)")]
+    Decode { message: String, body: String },
+
+    #[error("network error talking to the Starling API: {0}")]
+    Network(String),
+}
+
+impl StarlingError {
+    /// Build a `Decode` error keeping only the start of the offending body
+    pub fn decode(message: impl ToString, body: &str) -> Self {
+        StarlingError::Decode {
+            message: message.to_string(),
+            body: snippet(body),
+        }
+    }
+
+    /// Classify an unsuccessful HTTP response
+    pub fn from_status(status: u16, url: &str, body: &str, retry_after: Option<Duration>) -> Self {
+        match status {
+            401 | 403 => StarlingError::Auth { status },
+            404 => StarlingError::NotFound {
+                url: url.to_string(),
+            },
+            429 => StarlingError::RateLimited { retry_after },
+            _ => StarlingError::Status {
+                status,
+                body: snippet(body),
+            },
+        }
+    }
+
+    /// The process exit code the CLI uses for this error, following `sysexits.h`
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            StarlingError::Auth { .. } => 77,        // EX_NOPERM
+            StarlingError::RateLimited { .. } => 75, // EX_TEMPFAIL
+            StarlingError::NotFound { .. } => 66,    // EX_NOINPUT
+            StarlingError::Status { .. } => 69,      // EX_UNAVAILABLE
+            StarlingError::Decode { .. } => 65,      // EX_DATAERR
+            StarlingError::Network(_) => 75,         // EX_TEMPFAIL
+        }
+    }
+}
+
+fn snippet(body: &str) -> String {
+    let mut snippet: String = body.chars().take(SNIPPET_LENGTH).collect();
+    if body.chars().count() > SNIPPET_LENGTH {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_classifies_statuses() {
+        assert!(matches!(
+            StarlingError::from_status(401, "url", "", None),
+            StarlingError::Auth { status: 401 }
+        ));
+        assert!(matches!(
+            StarlingError::from_status(404, "url", "", None),
+            StarlingError::NotFound { .. }
+        ));
+        let error = StarlingError::from_status(429, "url", "", Some(Duration::from_secs(30)));
+        assert_eq!(
+            error.to_string(),
+            "rate limited by the Starling API: retry after 30s"
+        );
+        assert_eq!(error.exit_code(), 75);
+    }
+
+    #[test]
+    fn it_keeps_a_snippet_of_undecodable_bodies() {
+        let body = "x".repeat(1000);
+        match StarlingError::decode("expected value", &body) {
+            StarlingError::Decode { body, .. } => assert_eq!(body.chars().count(), 201),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Recording Starling API responses as fixtures for `StarlingMockClient`
+//!
+//! Recorded fixtures can be redacted before they're shared: identifying values are replaced with
+//! placeholders, keeping UIDs and names consistent so the fixture still describes the same
+//! accounts, categories and counterparties.
+
+use super::{
+    account::SignedCurrencyAndAmount,
+    client::{StarlingClient, StarlingMockClient},
+    error::StarlingError,
+    identity::ACCOUNT_DETAILS,
+};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Fetch every account with its balance, spaces and the feed items since `since` into a mock
+/// client, with the account holder and identifiers if the token grants the scopes for them
+pub async fn record(
+    client: &impl StarlingClient,
+    since: DateTime<Utc>,
+) -> Result<StarlingMockClient, StarlingError> {
+    let mut fixture = StarlingMockClient::new();
+    let details = client.token_identity().await?.grants(&ACCOUNT_DETAILS);
+    if details {
+        let holder = client.account_holder().await?;
+        fixture = fixture.with_account_holder(holder, &client.account_holder_name().await?);
+    }
+
+    for account in client.accounts().await? {
+        if details {
+            let identifiers = client.account_identifiers(&account.uid).await?;
+            fixture = fixture.with_identifiers(&account.uid, identifiers);
+        }
+        let balance = client.balance(&account.uid).await?;
+        let spaces = client.spaces(&account.uid).await?;
+
+        let mut items = client
+            .transactions_since(&account.uid, &account.default_category, since)
+            .await?;
+        for space in spaces.all() {
+            items.append(
+                client
+                    .transactions_since(&account.uid, &space.uid, since)
+                    .await?,
+            );
+        }
+        // stdout may be the fixture itself
+        for skipped in items.skipped.iter() {
+            eprintln!("Skipping {}", skipped);
+        }
+
+        fixture = fixture
+            .with_balance(&account.uid, balance)
+            .with_spaces(&account.uid, spaces)
+            .with_feed_items(&account.uid, items.feed_items)
+            .with_account(account);
+    }
+
+    Ok(fixture)
+}
+
+/// Replace names, UIDs, bank identifiers, references, notes and amounts in a fixture with
+/// placeholders, and drop feed item fields that aren't modelled
+pub fn redact(fixture: &StarlingMockClient) -> StarlingMockClient {
+    let mut uids = Placeholders::new(|n| format!("00000000-0000-4000-8000-{:012x}", n));
+    let mut sort_codes = Placeholders::new(|n| format!("{:06}", n));
+    let mut account_numbers = Placeholders::new(|n| format!("{:08}", n));
+    let mut ibans = Placeholders::new(|n| format!("GB00TEST{:014}", n));
+    let mut accounts = Placeholders::new(|n| format!("Account {}", n));
+    let mut space_names = Placeholders::new(|n| format!("Space {}", n));
+    let mut counterparties = Placeholders::new(|n| format!("Counterparty {}", n));
+    let mut rng = rand::thread_rng();
+    let mut redacted = StarlingMockClient::new();
+
+    for account in &fixture.accounts {
+        let mut account = account.clone();
+        account.uid = uids.get(&account.uid);
+        account.default_category = uids.get(&account.default_category);
+        account.name = accounts.get(&account.name);
+        redacted = redacted.with_account(account);
+    }
+
+    if let (Some(holder), Some(_)) = (&fixture.account_holder, &fixture.account_holder_name) {
+        let mut holder = holder.clone();
+        holder.uid = uids.get(&holder.uid);
+        redacted = redacted.with_account_holder(holder, "Account Holder");
+    }
+
+    for (account_uid, identifiers) in &fixture.identifiers {
+        let mut identifiers = identifiers.clone();
+        identifiers.bank_identifier = sort_codes.get(&identifiers.bank_identifier);
+        identifiers.account_identifier = account_numbers.get(&identifiers.account_identifier);
+        identifiers.iban = identifiers.iban.map(|iban| ibans.get(&iban));
+        redacted = redacted.with_identifiers(&uids.get(account_uid), identifiers);
+    }
+
+    for (account_uid, balance) in &fixture.balances {
+        let mut balance = balance.clone();
+        for amount in [
+            &mut balance.cleared,
+            &mut balance.effective,
+            &mut balance.pending,
+            &mut balance.total_cleared,
+            &mut balance.overdraft,
+            &mut balance.total_effective,
+        ] {
+            redact_amount(amount, &mut rng);
+        }
+        redacted = redacted.with_balance(&uids.get(account_uid), balance);
+    }
+
+    for (account_uid, spaces) in &fixture.spaces {
+        let mut spaces = spaces.clone();
+        for goal in spaces.savings_goals.iter_mut() {
+            goal.uid = uids.get(&goal.uid);
+            goal.name = space_names.get(&goal.name);
+            redact_amount(&mut goal.total_saved, &mut rng);
+            if let Some(target) = goal.target.as_mut() {
+                redact_amount(target, &mut rng);
+            }
+        }
+        for space in spaces.spending_spaces.iter_mut() {
+            space.uid = uids.get(&space.uid);
+            space.name = space_names.get(&space.name);
+            redact_amount(&mut space.balance, &mut rng);
+        }
+        redacted = redacted.with_spaces(&uids.get(account_uid), spaces);
+    }
+
+    for (account_uid, items) in &fixture.feed_items {
+        let items = items
+            .iter()
+            .map(|item| {
+                let mut item = item.clone();
+                item.uid = uids.get(&item.uid);
+                item.category_uid = item.category_uid.map(|uid| uids.get(&uid));
+                item.counterparty_uid = item.counterparty_uid.map(|uid| uids.get(&uid));
+                item.counterparty_name = counterparties.get(&item.counterparty_name);
+                // own accounts keep matching their redacted identifiers
+                item.counterparty_identifier =
+                    item.counterparty_identifier.map(|id| sort_codes.get(&id));
+                item.counterparty_sub_identifier = item.counterparty_sub_identifier.map(|id| {
+                    match id.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                        true => ibans.get(&id),
+                        false => account_numbers.get(&id),
+                    }
+                });
+                item.reference = item.reference.map(|_| String::from("REFERENCE"));
+                item.user_note = item.user_note.map(|_| String::from("NOTE"));
+                item.transacting_user_uid = item.transacting_user_uid.map(|uid| uids.get(&uid));
+                if let Some(round_up) = item.round_up.as_mut() {
+                    round_up.goal_category_uid = uids.get(&round_up.goal_category_uid);
+                }
+                if let Some(batch) = item.batch_payment_details.as_mut() {
+                    batch.batch_payment_uid = uids.get(&batch.batch_payment_uid);
+                }
+                // unmodelled fields may identify the counterparty, e.g. by account number
+                item.other.clear();
+                item.raw = None;
+                item.amount.minor_units = rng.gen_range(1..=100_000);
+                if let Some(source_amount) = item.source_amount.as_mut() {
+                    source_amount.minor_units = rng.gen_range(1..=100_000);
+                }
+                // round up the redacted amount to the next whole unit, as the original was
+                if let Some(round_up) = item.round_up.as_mut() {
+                    round_up.amount.minor_units = (100 - item.amount.minor_units % 100) % 100;
+                }
+                // where and at what kind of merchant the card was used
+                item.country = None;
+                item.mcc = None;
+                item
+            })
+            .collect();
+        redacted = redacted.with_feed_items(&uids.get(account_uid), items);
+    }
+
+    redacted
+}
+
+// Replace a balance amount, keeping zero amounts (e.g. no overdraft) as they are
+fn redact_amount(amount: &mut SignedCurrencyAndAmount, rng: &mut impl Rng) {
+    if amount.minor_units != 0 {
+        amount.minor_units = rng.gen_range(1..=1_000_000);
+    }
+}
+
+// Maps each original value to a numbered placeholder, the same one every time it's seen
+struct Placeholders<F: Fn(usize) -> String> {
+    seen: HashMap<String, String>,
+    make: F,
+}
+
+impl<F: Fn(usize) -> String> Placeholders<F> {
+    fn new(make: F) -> Self {
+        Self {
+            seen: HashMap::new(),
+            make,
+        }
+    }
+
+    fn get(&mut self, value: &str) -> String {
+        let next = self.seen.len() + 1;
+        self.seen
+            .entry(value.to_string())
+            .or_insert_with(|| (self.make)(next))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::fixture_client;
+
+    #[tokio::test]
+    async fn it_records_a_fixture_from_a_client() {
+        let since = "2023-01-01T00:00:00Z".parse().unwrap();
+        let recorded = record(&fixture_client(), since).await.unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&recorded).unwrap(),
+            serde_json::to_value(fixture_client()).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_redacts_consistently() {
+        let mut original = fixture_client();
+        let account_uid = original.accounts[0].uid.to_owned();
+        // a card payment abroad, rounded up into a space
+        let payment = &mut original.feed_items.get_mut(&account_uid).unwrap()[1];
+        payment.source_amount =
+            Some(serde_json::from_str(r#"{ "currency": "EUR", "minorUnits": 250000 }"#).unwrap());
+        payment.round_up = Some(serde_json::from_str(
+            r#"{ "goalCategoryUid": "3f0b6d8e-0000-4000-8000-0000000000c2", "amount": { "currency": "GBP", "minorUnits": 50 } }"#,
+        ).unwrap());
+        payment.country = Some(String::from("FR"));
+        payment.mcc = Some(5812);
+        let redacted = redact(&original);
+        let json = serde_json::to_string(&redacted).unwrap();
+
+        let account = &original.accounts[0];
+        for secret in [
+            account.uid.as_str(),
+            account.name.as_str(),
+            "Pizza Place",
+            "Holiday",
+            "Jane Doe",
+            "12345678",
+            "GB00SRLG",
+        ] {
+            assert!(!json.contains(secret), "{} leaked", secret);
+        }
+
+        let uid = &redacted.accounts[0].uid;
+        assert_eq!(redacted.accounts[0].name, "Account 1");
+        assert!(redacted.balances.contains_key(uid));
+        assert_eq!(redacted.feed_items[uid].len(), 4);
+        assert_eq!(redacted.spaces[uid].savings_goals[0].name, "Space 1");
+        assert!(redacted.feed_items[uid]
+            .iter()
+            .all(|item| item.counterparty_name.starts_with("Counterparty ")));
+
+        let payment = &redacted.feed_items[uid][1];
+        // redacted amounts are at most 100000
+        assert_ne!(payment.source_amount.as_ref().unwrap().minor_units, 250000);
+        assert_eq!(
+            payment.round_up.as_ref().unwrap().amount.minor_units,
+            (100 - payment.amount.minor_units % 100) % 100
+        );
+        assert_eq!(payment.country, None);
+        assert_eq!(payment.mcc, None);
+    }
+}
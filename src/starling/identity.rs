@@ -0,0 +1,176 @@
+//! Structures for processing the `/api/v2/identity/token` endpoint, and the token scopes each
+//! feature of the app needs
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Represents a feature of the app and the token scopes it needs
+///
+/// Tokens must grant the scopes of required features; optional features are skipped without them.
+pub struct Feature {
+    pub name: &'static str,
+    pub scopes: &'static [&'static str],
+    pub required: bool,
+}
+
+/// The account holder's name and each account's sort code, account number, IBAN and BIC
+pub const ACCOUNT_DETAILS: Feature = Feature {
+    name: "account details",
+    scopes: &[
+        "account-holder-type:read",
+        "account-holder-name:read",
+        "account-identifier:read",
+    ],
+    required: false,
+};
+
+/// The features an account's token is used for, and their scopes
+pub const FEATURES: &[Feature] = &[
+    Feature {
+        name: "accounts",
+        scopes: &["account-list:read"],
+        required: true,
+    },
+    Feature {
+        name: "balances",
+        scopes: &["balance:read"],
+        required: true,
+    },
+    Feature {
+        name: "transactions",
+        scopes: &["transaction:read"],
+        required: true,
+    },
+    Feature {
+        name: "spaces",
+        scopes: &["space:read", "savings-goal:read"],
+        required: true,
+    },
+    ACCOUNT_DETAILS,
+];
+
+/// Represents what an access token is for: its account holder, scopes and expiry
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TokenIdentity {
+    #[serde(rename = "accountHolderUid")]
+    pub account_holder_uid: String,
+    #[serde(default)]
+    pub authenticated: bool,
+    /// When the token expires; personal access tokens don't
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(rename = "expiresInSeconds")]
+    pub expires_in_seconds: Option<i64>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl TokenIdentity {
+    /// An identity granting every scope in `FEATURES`
+    pub fn with_all_scopes(account_holder_uid: &str) -> Self {
+        Self {
+            account_holder_uid: account_holder_uid.to_string(),
+            authenticated: true,
+            scopes: FEATURES
+                .iter()
+                .flat_map(|feature| feature.scopes)
+                .map(|scope| scope.to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Return true if the token grants every scope the feature needs
+    pub fn grants(&self, feature: &Feature) -> bool {
+        feature
+            .scopes
+            .iter()
+            .all(|scope| self.scopes.iter().any(|granted| granted == scope))
+    }
+
+    /// Check the token grants the scopes of every required feature
+    pub fn check_scopes(&self) -> Result<(), MissingScopes> {
+        self.check(FEATURES.iter().filter(|feature| feature.required))
+    }
+
+    /// Check the token grants the scopes of every feature, required or optional
+    pub fn check_all_scopes(&self) -> Result<(), MissingScopes> {
+        self.check(FEATURES.iter())
+    }
+
+    fn check<'a>(&self, features: impl Iterator<Item = &'a Feature>) -> Result<(), MissingScopes> {
+        let features: Vec<(String, Vec<String>)> = features
+            .filter_map(|feature| {
+                let missing: Vec<String> = feature
+                    .scopes
+                    .iter()
+                    .filter(|scope| !self.scopes.iter().any(|granted| granted == *scope))
+                    .map(|scope| scope.to_string())
+                    .collect();
+                (!missing.is_empty()).then(|| (feature.name.to_string(), missing))
+            })
+            .collect();
+
+        match features.is_empty() {
+            true => Ok(()),
+            false => Err(MissingScopes { features }),
+        }
+    }
+}
+
+/// Represents the scopes a token lacks, by feature
+#[derive(Error, Debug, PartialEq, Eq)]
+pub struct MissingScopes {
+    pub features: Vec<(String, Vec<String>)>,
+}
+
+impl fmt::Display for MissingScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let features: Vec<String> = self
+            .features
+            .iter()
+            .map(|(feature, scopes)| format!("{} needs {}", feature, scopes.join(", ")))
+            .collect();
+        write!(f, "the access token lacks scopes: {}", features.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reports_missing_scopes_by_feature() {
+        let mut identity = TokenIdentity::with_all_scopes("holder-uid");
+        assert_eq!(identity.check_scopes(), Ok(()));
+
+        // optional features are only checked when asked
+        identity
+            .scopes
+            .retain(|scope| scope != "account-identifier:read");
+        assert_eq!(identity.check_scopes(), Ok(()));
+        assert!(!identity.grants(&ACCOUNT_DETAILS));
+        assert!(identity.check_all_scopes().is_err());
+
+        identity
+            .scopes
+            .retain(|scope| scope != "transaction:read" && scope != "space:read");
+        let error = identity.check_scopes().unwrap_err();
+        assert_eq!(
+            error.features,
+            vec![
+                (
+                    String::from("transactions"),
+                    vec![String::from("transaction:read")]
+                ),
+                (String::from("spaces"), vec![String::from("space:read")]),
+            ]
+        );
+        assert_eq!(
+            error.to_string(),
+            "the access token lacks scopes: transactions needs transaction:read; spaces needs space:read"
+        );
+    }
+}
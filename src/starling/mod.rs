@@ -1,3 +1,8 @@
 pub mod account;
 pub mod client;
+pub mod error;
+pub mod fixture;
+pub mod identity;
+pub mod retry;
+pub mod space;
 pub mod transaction;
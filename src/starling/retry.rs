@@ -0,0 +1,169 @@
+//! Retry policy for requests to the Starling API
+//!
+//! Rate-limited requests wait for the server's `Retry-After`; transient failures (network errors
+//! and 5xx responses) back off exponentially with jitter.
+
+use super::error::StarlingError;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Represents how failed requests are retried
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further retry
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    /// Upper limit on a single delay; a server asking to wait longer fails the request instead
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_max_delay_ms() -> u64 {
+    30_000
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            base_delay_ms: default_base_delay_ms(),
+            max_delay_ms: default_max_delay_ms(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Return how long to wait before retrying after the given failed attempt (counting from 0),
+    /// or `None` if the error isn't transient, the retries are used up, or the server's
+    /// `Retry-After` is longer than `max_delay_ms`.
+    pub fn delay(&self, attempt: u32, error: &StarlingError) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        match error {
+            StarlingError::RateLimited {
+                retry_after: Some(retry_after),
+            } => (*retry_after <= Duration::from_millis(self.max_delay_ms)).then_some(*retry_after),
+            StarlingError::RateLimited { retry_after: None } | StarlingError::Network(_) => {
+                Some(self.backoff(attempt))
+            }
+            StarlingError::Status { status, .. } if *status >= 500 => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+
+    // Exponential backoff with "equal jitter": a random delay between half and all of the
+    // exponential delay, so that clients retrying together spread out.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt))
+            .min(self.max_delay_ms);
+        let half = exponential / 2;
+        let jitter = rand::thread_rng().gen_range(0..=exponential - half);
+
+        Duration::from_millis(half + jitter)
+    }
+}
+
+/// Parse a `Retry-After` header, given either as seconds or as an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_error() -> StarlingError {
+        StarlingError::Status {
+            status: 503,
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn it_backs_off_exponentially_within_limits() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+
+        for attempt in 0..10 {
+            let delay = policy.delay(attempt, &server_error()).unwrap();
+            let exponential = (100 * 2u64.pow(attempt)).min(1000);
+            assert!(delay >= Duration::from_millis(exponential / 2));
+            assert!(delay <= Duration::from_millis(exponential));
+        }
+        assert_eq!(policy.delay(10, &server_error()), None);
+        assert_eq!(RetryPolicy::none().delay(0, &server_error()), None);
+    }
+
+    #[test]
+    fn it_only_retries_transient_errors() {
+        let policy = RetryPolicy::default();
+        let rate_limited = StarlingError::RateLimited {
+            retry_after: Some(Duration::from_secs(7)),
+        };
+
+        assert_eq!(policy.delay(0, &rate_limited), Some(Duration::from_secs(7)));
+        // a wait longer than `max_delay_ms` fails the request rather than hanging the CLI
+        let rate_limited_for_hours = StarlingError::RateLimited {
+            retry_after: Some(Duration::from_secs(3 * 60 * 60)),
+        };
+        assert_eq!(policy.delay(0, &rate_limited_for_hours), None);
+        assert!(policy
+            .delay(0, &StarlingError::Network(String::from("reset")))
+            .is_some());
+        assert_eq!(policy.delay(0, &StarlingError::Auth { status: 401 }), None);
+        assert_eq!(
+            policy.delay(
+                0,
+                &StarlingError::Status {
+                    status: 400,
+                    body: String::new()
+                }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn it_parses_retry_after() {
+        assert_eq!(parse_retry_after(" 30 "), Some(Duration::from_secs(30)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
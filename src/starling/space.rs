@@ -0,0 +1,86 @@
+//! Structures for processing the `/api/v2/account/{accountUid}/spaces` and
+//! `/api/v2/account/{accountUid}/savings-goals` endpoints
+//!
+//! Spaces are savings goals and spending spaces kept apart from an account's main balance. Each
+//! has its own feed category, whose UID is the space's UID.
+
+use super::account::SignedCurrencyAndAmount;
+use serde::{Deserialize, Serialize};
+
+/// Represents the spaces of an account
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Spaces {
+    #[serde(rename = "savingsGoals", default)]
+    pub savings_goals: Vec<SavingsGoal>,
+    #[serde(rename = "spendingSpaces", default)]
+    pub spending_spaces: Vec<SpendingSpace>,
+}
+
+/// Represents a list of savings goals
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavingsGoals {
+    #[serde(rename = "savingsGoalList")]
+    pub savings_goal_list: Vec<SavingsGoal>,
+}
+
+/// Represents a savings goal
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavingsGoal {
+    #[serde(rename = "savingsGoalUid")]
+    pub uid: String,
+    pub name: String,
+    pub target: Option<SignedCurrencyAndAmount>,
+    #[serde(rename = "totalSaved")]
+    pub total_saved: SignedCurrencyAndAmount,
+    #[serde(rename = "savedPercentage")]
+    pub saved_percentage: Option<i64>,
+    pub state: Option<String>,
+}
+
+/// Represents a spending space
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpendingSpace {
+    #[serde(rename = "spaceUid")]
+    pub uid: String,
+    pub name: String,
+    pub balance: SignedCurrencyAndAmount,
+    #[serde(rename = "spendingSpaceType")]
+    pub spending_space_type: Option<String>,
+    pub state: Option<String>,
+}
+
+/// Represents the kind of a space
+#[derive(Debug, Clone, PartialEq, Eq, strum_macros::Display, strum_macros::EnumString)]
+pub enum SpaceKind {
+    SavingsGoal,
+    SpendingSpace,
+}
+
+/// A space of either kind, with the money held in it
+#[derive(Debug, Clone)]
+pub struct Space {
+    pub uid: String,
+    pub name: String,
+    pub kind: SpaceKind,
+    pub balance: SignedCurrencyAndAmount,
+}
+
+impl Spaces {
+    /// Every space, savings goals first
+    pub fn all(&self) -> Vec<Space> {
+        let goals = self.savings_goals.iter().map(|goal| Space {
+            uid: goal.uid.to_owned(),
+            name: goal.name.to_owned(),
+            kind: SpaceKind::SavingsGoal,
+            balance: goal.total_saved.clone(),
+        });
+        let spending = self.spending_spaces.iter().map(|space| Space {
+            uid: space.uid.to_owned(),
+            name: space.name.to_owned(),
+            kind: SpaceKind::SpendingSpace,
+            balance: space.balance.clone(),
+        });
+
+        goals.chain(spending).collect()
+    }
+}
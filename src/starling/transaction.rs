@@ -1,62 +1,187 @@
+pub use crate::currency::Currency;
+use crate::money::Money;
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
 
 /// Represents a single Transaction
-#[derive(Deserialize, Debug)]
+///
+/// Feed items that can't be decoded are skipped, rather than failing the whole page, and listed
+/// in `skipped` for the caller to report.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(from = "FeedItemValues")]
 pub struct StarlingTransactions {
     #[serde(rename = "feedItems")]
     pub feed_items: Vec<StarlingTransaction>,
+    #[serde(skip)]
+    pub skipped: Vec<SkippedItem>,
+}
+
+impl StarlingTransactions {
+    /// Add the feed items, and the skipped ones, of another page
+    pub fn append(&mut self, mut page: StarlingTransactions) {
+        self.feed_items.append(&mut page.feed_items);
+        self.skipped.append(&mut page.skipped);
+    }
+}
+
+impl From<Vec<StarlingTransaction>> for StarlingTransactions {
+    fn from(feed_items: Vec<StarlingTransaction>) -> Self {
+        Self {
+            feed_items,
+            skipped: Vec::new(),
+        }
+    }
+}
+
+/// Represents a feed item that couldn't be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedItem {
+    pub uid: String,
+    pub reason: String,
+}
+
+impl fmt::Display for SkippedItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "feed item {}: {}", self.uid, self.reason)
+    }
+}
+
+// A page of feed items before they're decoded one at a time
+#[derive(Deserialize)]
+struct FeedItemValues {
+    #[serde(rename = "feedItems")]
+    feed_items: Vec<Value>,
+}
+
+impl From<FeedItemValues> for StarlingTransactions {
+    fn from(values: FeedItemValues) -> Self {
+        let mut transactions = StarlingTransactions::default();
+        for item in values.feed_items {
+            let uid = item["feedItemUid"].as_str().unwrap_or("?").to_string();
+            match serde_json::from_value::<StarlingTransaction>(item.clone()) {
+                Ok(decoded) => transactions.feed_items.push(StarlingTransaction {
+                    raw: Some(item),
+                    ..decoded
+                }),
+                Err(e) => transactions.skipped.push(SkippedItem {
+                    uid,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        transactions
+    }
 }
 
 /// Represents a feed item returned from the API
-#[derive(Deserialize, Debug)]
+///
+/// Fields that aren't modelled here are kept in `other`, so that serialising a feed item gives
+/// back the whole payload.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StarlingTransaction {
     pub amount: CurrencyValue,
+    #[serde(rename = "batchPaymentDetails")]
+    pub batch_payment_details: Option<BatchPaymentDetails>,
+    #[serde(rename = "categoryUid")]
+    pub category_uid: Option<String>,
     #[serde(rename = "counterPartyName")]
     pub counterparty_name: String,
+    /// The counterparty's bank identifier, e.g. sort code or BIC
+    #[serde(rename = "counterPartySubEntityIdentifier")]
+    pub counterparty_identifier: Option<String>,
+    /// The counterparty's account identifier, e.g. account number or IBAN
+    #[serde(rename = "counterPartySubEntitySubIdentifier")]
+    pub counterparty_sub_identifier: Option<String>,
     #[serde(rename = "counterPartyType")]
     pub counterparty_type: String,
     #[serde(rename = "counterPartyUid")]
     pub counterparty_uid: Option<String>,
+    /// The ISO 3166 code of the country the transaction took place in
+    pub country: Option<String>,
     pub direction: Direction,
+    #[serde(rename = "hasAttachment", default)]
+    pub has_attachment: bool,
+    #[serde(rename = "hasReceipt", default)]
+    pub has_receipt: bool,
+    /// The merchant category code of a card payment
+    #[serde(default, deserialize_with = "number_or_string")]
+    pub mcc: Option<i32>,
     pub reference: Option<String>,
+    #[serde(rename = "roundUp")]
+    pub round_up: Option<RoundUp>,
+    #[serde(rename = "settlementTime")]
+    pub settlement_time: Option<DateTime<Utc>>,
+    /// How the money moved, e.g. `MASTER_CARD` or `FASTER_PAYMENTS_IN`
+    pub source: Option<String>,
+    /// The amount in the currency of the purchase, e.g. for card payments abroad
+    #[serde(rename = "sourceAmount")]
+    pub source_amount: Option<CurrencyValue>,
     #[serde(rename = "spendingCategory")]
     pub spending_category: String,
     pub status: Status,
+    /// The account holder's app user who made the transaction, for joint accounts
+    #[serde(rename = "transactingApplicationUserUid")]
+    pub transacting_user_uid: Option<String>,
     #[serde(rename = "transactionTime")]
     pub transaction_time: DateTime<Utc>,
     #[serde(rename = "feedItemUid")]
     pub uid: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
     #[serde(rename = "userNote")]
     pub user_note: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+    /// The feed item as the API sent it, if it came from a page of feed items
+    #[serde(skip)]
+    pub raw: Option<Value>,
+}
+
+/// Represents the amount rounded up from a payment into a savings goal
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RoundUp {
+    #[serde(rename = "goalCategoryUid")]
+    pub goal_category_uid: String,
+    pub amount: CurrencyValue,
+}
+
+/// Represents the batch a payment was made in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchPaymentDetails {
+    #[serde(rename = "batchPaymentUid")]
+    pub batch_payment_uid: String,
+    #[serde(rename = "batchPaymentType")]
+    pub batch_payment_type: String,
 }
 
 /// Represents available currency values
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CurrencyValue {
     #[serde(rename = "minorUnits")]
-    pub pennies: u32,
+    pub minor_units: i64,
     pub currency: Currency,
 }
 
-/// Represents available currencies
-#[derive(Deserialize, Debug, strum_macros::Display)]
-pub enum Currency {
-    GBP,
-    USD,
-}
-
 /// Represents transaction credit or debit
-#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub enum Direction {
     #[serde(rename = "IN")]
     In,
     #[serde(rename = "OUT")]
     Out,
+    /// A direction this version doesn't know about
+    #[serde(untagged)]
+    Other(String),
 }
 
 /// Represents transaction status
-#[derive(Deserialize, Debug, strum_macros::Display)]
+///
+/// Displays as the name stored in the database, e.g. `Settled`, or as Starling's value for
+/// statuses this version doesn't know about.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum Status {
     #[serde(rename = "UPCOMING")]
     Upcoming,
@@ -68,35 +193,153 @@ pub enum Status {
     Declined,
     #[serde(rename = "REVERSED")]
     Reversed,
+    /// A status this version doesn't know about, e.g. `ACCOUNT_CHECK`
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Status::Upcoming => "Upcoming",
+            Status::Pending => "Pending",
+            Status::Settled => "Settled",
+            Status::Declined => "Declined",
+            Status::Reversed => "Reversed",
+            Status::Other(status) => status,
+        };
+        f.pad(name)
+    }
 }
 
 /// Compute the transaction amount
 impl StarlingTransaction {
-    pub fn amount(&self) -> f32 {
+    /// The signed amount: negative for money leaving the account, and unsigned if the direction
+    /// isn't known
+    pub fn amount(&self) -> Money {
         let direction = match self.direction {
-            Direction::In => 1.0,
-            Direction::Out => -1.0,
+            Direction::Out => -1,
+            Direction::In | Direction::Other(_) => 1,
         };
-        direction * self.amount.pennies as f32 / 100.0
+        Money::new(direction * self.amount.minor_units, &self.currency())
     }
 
     pub fn currency(&self) -> String {
         self.amount.currency.to_string()
     }
+
+    /// The signed amount in the currency of the purchase, if it differs from the account's
+    pub fn source_amount(&self) -> Option<Money> {
+        let source = self.source_amount.as_ref()?;
+        if source.currency == self.amount.currency {
+            return None;
+        }
+        let amount = Money::new(source.minor_units, &source.currency.to_string());
+        Some(match self.direction {
+            Direction::Out => -amount,
+            Direction::In | Direction::Other(_) => amount,
+        })
+    }
 }
 
-impl ToString for StarlingTransaction {
-    fn to_string(&self) -> String {
-        format!(
-            "{} : £{}.{} {} {}",
+// Deserialise a number that may be sent as a JSON number or a string
+fn number_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i32>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        Value::Number(number) => number
+            .as_i64()
+            .and_then(|number| i32::try_from(number).ok())
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid number `{}`", number))),
+        Value::String(string) => string
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("invalid number `{}`", string))),
+        other => Err(serde::de::Error::custom(format!(
+            "expected a number, found `{}`",
+            other
+        ))),
+    }
+}
+
+impl fmt::Display for StarlingTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} : {} {} {}",
             self.transaction_time.format("%Y-%m-%d"),
-            self.amount.pennies / 100,
-            self.amount.pennies % 100,
+            Money::new(self.amount.minor_units, &self.currency()),
             match self.direction {
                 Direction::In => "<-",
                 Direction::Out => "->",
+                Direction::Other(_) => "--",
             },
             self.counterparty_name
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(direction: &str, status: &str, currency: &str) -> String {
+        format!(
+            r#"{{
+                "feedItemUid": "feed-uid",
+                "amount": {{ "currency": "{}", "minorUnits": 1250 }},
+                "counterPartyName": "Pizza Place",
+                "counterPartyType": "MERCHANT",
+                "direction": "{}",
+                "spendingCategory": "EATING_OUT",
+                "status": "{}",
+                "transactionTime": "2023-05-01T12:00:00.000Z"
+            }}"#,
+            currency, direction, status
+        )
+    }
+
+    #[test]
+    fn it_keeps_unknown_values() {
+        let json = item("SIDEWAYS", "ACCOUNT_CHECK", "XBT");
+        let item: StarlingTransaction = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(item.direction, Direction::Other(String::from("SIDEWAYS")));
+        assert_eq!(item.status, Status::Other(String::from("ACCOUNT_CHECK")));
+        assert_eq!(item.status.to_string(), "ACCOUNT_CHECK");
+        assert_eq!(item.amount.currency, Currency::Other(String::from("XBT")));
+
+        // and serialises them back unchanged
+        let value = serde_json::to_value(&item).unwrap();
+        assert_eq!(value["status"], "ACCOUNT_CHECK");
+        assert_eq!(value["amount"]["currency"], "XBT");
+    }
+
+    #[test]
+    fn it_reads_known_values() {
+        let item: StarlingTransaction =
+            serde_json::from_str(&item("OUT", "SETTLED", "EUR")).unwrap();
+
+        assert_eq!(item.status, Status::Settled);
+        assert_eq!(item.status.to_string(), "Settled");
+        assert_eq!(item.amount().to_string(), "EUR -12.50");
+    }
+
+    #[test]
+    fn it_skips_undecodable_items() {
+        let json = format!(
+            r#"{{ "feedItems": [{}, {{ "feedItemUid": "broken" }}] }}"#,
+            item("IN", "REFUNDED", "GBP")
+        );
+        let transactions: StarlingTransactions = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(transactions.feed_items.len(), 1);
+        assert_eq!(
+            transactions.feed_items[0].status,
+            Status::Other(String::from("REFUNDED"))
+        );
+        assert_eq!(transactions.skipped.len(), 1);
+        assert_eq!(transactions.skipped[0].uid, "broken");
+    }
+}